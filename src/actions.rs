@@ -0,0 +1,143 @@
+// everything the editor can do that has a key or a place in the command palette
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Save,
+    SaveAs,
+    NewFile,
+    OpenFile,
+    Quit,
+    Find,
+    FindNext,
+    FindPrevious,
+    Replace,
+    SearchFiles,
+    GotoLine,
+    Undo,
+    Redo,
+    ToggleHex,
+    ToggleExplorer,
+    OpenConsole,
+    CommandPalette,
+    // the menus in the order of their titles
+    OpenMenu(usize),
+    // an index into theme::THEMES
+    Theme(usize),
+    ToggleSaveOnUnfocus,
+    SetOption,
+    SetLanguage,
+    WriteMode,
+    EditMode,
+    CursorUp,
+    CursorDown,
+    CursorLeft,
+    CursorRight,
+    NextWord,
+    PreviousWord,
+    LineStart,
+    LineEnd,
+    MoveLineUp,
+    MoveLineDown,
+    DeleteLine,
+    IndentLine,
+    OutdentLine,
+    MatchBracket,
+    ToggleComment,
+}
+
+// the names used in the config file and shown in the command palette, in the
+// order the palette lists them
+pub const ACTIONS: &[(Action, &str, &str)] = &[
+    (Action::Save, "save", "Save"),
+    (Action::SaveAs, "save_as", "Save as..."),
+    (Action::NewFile, "new_file", "New file"),
+    (Action::OpenFile, "open_file", "Open file"),
+    (Action::Quit, "quit", "Quit"),
+    (Action::Find, "find", "Find"),
+    (Action::FindNext, "find_next", "Find next match"),
+    (Action::FindPrevious, "find_previous", "Find previous match"),
+    (Action::Replace, "replace", "Replace..."),
+    (Action::SearchFiles, "search_files", "Search in files..."),
+    (Action::GotoLine, "goto_line", "Go to line..."),
+    (Action::Undo, "undo", "Undo"),
+    (Action::Redo, "redo", "Redo"),
+    (Action::ToggleHex, "toggle_hex", "Toggle hex mode"),
+    (
+        Action::ToggleExplorer,
+        "toggle_explorer",
+        "Toggle file explorer",
+    ),
+    (Action::OpenConsole, "open_console", "Open console"),
+    (Action::CommandPalette, "command_palette", "Command palette"),
+    (Action::OpenMenu(0), "open_file_menu", "Open File menu"),
+    (Action::OpenMenu(1), "open_edit_menu", "Open Edit menu"),
+    (Action::OpenMenu(2), "open_color_menu", "Open Color menu"),
+    (
+        Action::OpenMenu(3),
+        "open_settings_menu",
+        "Open Settings menu",
+    ),
+    (Action::Theme(0), "theme_pico", "Theme: Pico"),
+    (Action::Theme(1), "theme_midnight", "Theme: Midnight"),
+    (Action::Theme(2), "theme_forest", "Theme: Forest"),
+    (
+        Action::ToggleSaveOnUnfocus,
+        "toggle_save_on_unfocus",
+        "Toggle save on unfocus",
+    ),
+    (Action::SetOption, "set_option", "Set option..."),
+    (Action::SetLanguage, "set_language", "Change language..."),
+    (Action::WriteMode, "write_mode", "Switch to write mode"),
+    (Action::EditMode, "edit_mode", "Switch to edit mode"),
+    (Action::CursorUp, "cursor_up", "Cursor up"),
+    (Action::CursorDown, "cursor_down", "Cursor down"),
+    (Action::CursorLeft, "cursor_left", "Cursor left"),
+    (Action::CursorRight, "cursor_right", "Cursor right"),
+    (Action::NextWord, "next_word", "Go to next word"),
+    (Action::PreviousWord, "previous_word", "Go to previous word"),
+    (Action::LineStart, "line_start", "Go to start of line"),
+    (Action::LineEnd, "line_end", "Go to end of line"),
+    (Action::MoveLineUp, "move_line_up", "Move line up"),
+    (Action::MoveLineDown, "move_line_down", "Move line down"),
+    (Action::DeleteLine, "delete_line", "Delete line"),
+    (Action::IndentLine, "indent_line", "Indent line"),
+    (Action::OutdentLine, "outdent_line", "Outdent line"),
+    (
+        Action::MatchBracket,
+        "match_bracket",
+        "Go to matching bracket",
+    ),
+    (Action::ToggleComment, "toggle_comment", "Toggle comment"),
+];
+
+impl Action {
+    // the actions that can change the lines, the others only move around
+    pub fn edits_text(&self) -> bool {
+        matches!(
+            self,
+            Action::Undo
+                | Action::Redo
+                | Action::ToggleComment
+                | Action::ToggleHex
+                | Action::MoveLineUp
+                | Action::MoveLineDown
+                | Action::DeleteLine
+                | Action::IndentLine
+                | Action::OutdentLine
+        )
+    }
+}
+
+pub fn from_id(id: &str) -> Option<Action> {
+    ACTIONS
+        .iter()
+        .find(|(_, action_id, _)| *action_id == id)
+        .map(|(action, _, _)| *action)
+}
+
+pub fn name_of(action: Action) -> &'static str {
+    ACTIONS
+        .iter()
+        .find(|(known, _, _)| *known == action)
+        .map(|(_, _, name)| *name)
+        .unwrap_or("?")
+}
@@ -0,0 +1,211 @@
+use crate::language::Language;
+
+// the brackets of a buffer outside of strings and comments, and which of
+// them belong together
+pub struct Brackets {
+    // line, char and the bracket itself, in the order they appear
+    found: Vec<(usize, usize, char)>,
+    partners: Vec<Option<usize>>,
+    // the opening brackets still waiting for their closing one at the end,
+    // innermost last
+    open: Vec<usize>,
+}
+
+impl Brackets {
+    pub fn scan(lines: &[String], language: &Language) -> Self {
+        let mut found = Vec::new();
+        let mut partners = Vec::new();
+        // the brackets still waiting for their closing one, with that one
+        let mut open: Vec<(usize, char)> = Vec::new();
+        let mut in_comment = false;
+
+        for (l, line) in lines.iter().enumerate() {
+            // strings end with their line
+            let mut quote = None;
+            let mut chars = line.char_indices().enumerate();
+
+            while let Some((i, (byte, c))) = chars.next() {
+                let rest = &line[byte..];
+
+                if in_comment {
+                    if let Some((_, end)) = language.block_comment {
+                        if rest.starts_with(end) {
+                            in_comment = false;
+                            skip(&mut chars, end.chars().count());
+                        }
+                    }
+                    continue;
+                }
+
+                if let Some(q) = quote {
+                    match c {
+                        '\\' => _ = chars.next(),
+                        c if c == q => quote = None,
+                        _ => {}
+                    }
+                    continue;
+                }
+
+                // block comments first, lua's `--[[` starts like a line comment
+                if let Some((start, _)) = language.block_comment {
+                    if rest.starts_with(start) {
+                        in_comment = true;
+                        skip(&mut chars, start.chars().count());
+                        continue;
+                    }
+                }
+                if language
+                    .line_comment
+                    .is_some_and(|token| rest.starts_with(token))
+                {
+                    break;
+                }
+
+                // 'x' and '\n' are skipped, lifetimes like 'a aren't closed
+                if language.char_literals && c == '\'' {
+                    if let Some(len) = char_literal_len(rest) {
+                        skip(&mut chars, len);
+                        continue;
+                    }
+                }
+
+                if language.quotes.contains(&c) {
+                    quote = Some(c);
+                } else if let Some(close) = language.closing_of(c) {
+                    open.push((found.len(), close));
+                    found.push((l, i, c));
+                    partners.push(None);
+                } else if language.opening_of(c).is_some() {
+                    // a closing bracket that doesn't fit stays unmatched
+                    if open.last().is_some_and(|(_, close)| *close == c) {
+                        let (opening, _) = open.pop().unwrap();
+                        partners[opening] = Some(found.len());
+                        partners.push(Some(opening));
+                    } else {
+                        partners.push(None);
+                    }
+                    found.push((l, i, c));
+                }
+            }
+        }
+
+        let open = open.into_iter().map(|(opening, _)| opening).collect();
+        Self {
+            found,
+            partners,
+            open,
+        }
+    }
+
+    // the bracket that belongs to the one at a position
+    pub fn partner(&self, line: usize, char: usize) -> Option<(usize, usize)> {
+        let i = self
+            .found
+            .binary_search_by_key(&(line, char), |(l, c, _)| (*l, *c))
+            .ok()?;
+        self.partners[i].map(|p| (self.found[p].0, self.found[p].1))
+    }
+
+    // the innermost bracket in front of a position that isn't closed before it
+    pub fn open_before(&self, line: usize, char: usize) -> Option<(usize, usize, char)> {
+        let before = self
+            .found
+            .partition_point(|(l, c, _)| (*l, *c) < (line, char));
+        (0..before)
+            .rev()
+            .find(|i| match self.partners[*i] {
+                // closed at or after the position, or the closing one itself
+                Some(partner) => partner >= before,
+                None => self.open.contains(i),
+            })
+            .map(|i| self.found[i])
+    }
+
+    // the first bracket without a partner
+    pub fn unbalanced(&self) -> Option<(usize, usize, char)> {
+        self.partners
+            .iter()
+            .position(Option::is_none)
+            .map(|i| self.found[i])
+    }
+}
+
+// moves past the rest of a token of `len` chars whose first char was just read
+fn skip(chars: &mut impl Iterator, len: usize) {
+    for _ in 1..len {
+        chars.next();
+    }
+}
+
+// how many chars the char literal at the start of `rest` takes
+fn char_literal_len(rest: &str) -> Option<usize> {
+    let mut chars = rest.chars().skip(1);
+    match chars.next()? {
+        // the escaped char can be a quote itself
+        '\\' => {
+            chars.next()?;
+            Some(chars.position(|c| c == '\'')? + 4)
+        }
+        _ => (chars.next()? == '\'').then_some(3),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::language_of;
+
+    fn scan(file_name: &str, text: &str) -> Brackets {
+        let lines: Vec<String> = text.lines().map(String::from).collect();
+        Brackets::scan(&lines, language_of(file_name))
+    }
+
+    #[test]
+    fn pairs_nested_brackets() {
+        let brackets = scan("a.rs", "fn a() {\n    b[0];\n}");
+
+        assert_eq!(brackets.partner(0, 4), Some((0, 5)));
+        assert_eq!(brackets.partner(0, 7), Some((2, 0)));
+        assert_eq!(brackets.partner(2, 0), Some((0, 7)));
+        assert_eq!(brackets.partner(1, 5), Some((1, 7)));
+        assert_eq!(brackets.partner(1, 0), None);
+        assert_eq!(brackets.unbalanced(), None);
+    }
+
+    #[test]
+    fn reports_the_first_unbalanced_bracket() {
+        assert_eq!(scan("a.rs", "fn a() {\n").unbalanced(), Some((0, 7, '{')));
+        assert_eq!(scan("a.rs", "(]\n").unbalanced(), Some((0, 0, '(')));
+        assert_eq!(scan("a.rs", "())").unbalanced(), Some((0, 2, ')')));
+    }
+
+    #[test]
+    fn finds_the_innermost_open_bracket() {
+        let brackets = scan(
+            "a.rs",
+            "fn a() {\n    match b {\n        c => [d],\n    }\n}",
+        );
+        assert_eq!(brackets.open_before(3, 4), Some((1, 12, '{')));
+        assert_eq!(brackets.open_before(2, 14), Some((2, 13, '[')));
+        assert_eq!(brackets.open_before(4, 0), Some((0, 7, '{')));
+        assert_eq!(brackets.open_before(4, 1), None);
+        assert_eq!(scan("a.rs", "a(b)").open_before(0, 4), None);
+        assert_eq!(scan("a.rs", "a(b").open_before(0, 3), Some((0, 1, '(')));
+    }
+
+    #[test]
+    fn skips_strings_and_comments() {
+        let text = "let s = \"}\"; // )\n/* ( */ let t = \"\\\"(\";";
+        assert_eq!(scan("a.rs", text).unbalanced(), None);
+        assert_eq!(scan("a.py", "x = '(' # [").unbalanced(), None);
+        assert_eq!(scan("a.lua", "--[[ ( ]] x = {}").unbalanced(), None);
+    }
+
+    #[test]
+    fn skips_char_literals_but_not_lifetimes() {
+        let text = "match c { '\"' => '(', '\\'' => '}', _ => ']' }";
+        assert_eq!(scan("a.rs", text).unbalanced(), None);
+        assert_eq!(scan("a.rs", "fn a<'a>(b: &'a str) {}").unbalanced(), None);
+        assert_eq!(scan("a.c", "if (c == '{') {}").unbalanced(), None);
+    }
+}
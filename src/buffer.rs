@@ -0,0 +1,586 @@
+use std::fs::{self, File};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::encoding::{self, Encoding};
+use crate::functions::{hash_of, modified_time};
+use crate::hexmode::HexEditor;
+use crate::history::History;
+use crate::indent::Indent;
+use crate::language::{language_of, Language};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "LF",
+            LineEnding::Crlf => "CRLF",
+        }
+    }
+}
+
+// everything about the file that isn't part of the lines themselves
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FileFormat {
+    pub encoding: Encoding,
+    pub line_ending: LineEnding,
+    pub bom: bool,
+    pub final_newline: bool,
+}
+
+impl FileFormat {
+    pub fn new() -> Self {
+        Self {
+            encoding: Encoding::Utf8,
+            line_ending: LineEnding::Lf,
+            bom: false,
+            final_newline: true,
+        }
+    }
+
+    // short description for the status bar, e.g. "UTF-8 BOM CRLF noeol"
+    pub fn describe(&self) -> String {
+        let mut description = self.encoding.name().to_string();
+        if self.bom {
+            description.push_str(" BOM");
+        }
+        description.push(' ');
+        description.push_str(self.line_ending.name());
+        if !self.final_newline {
+            description.push_str(" noeol");
+        }
+        description
+    }
+}
+
+// what the file on disk looked like when it was last read or written
+struct DiskState {
+    modified: Option<SystemTime>,
+    hash: u64,
+}
+
+impl DiskState {
+    fn of(file_path: &Path, bytes: &[u8]) -> Self {
+        Self {
+            modified: modified_time(file_path),
+            hash: hash_of(bytes),
+        }
+    }
+}
+
+pub struct Buffer {
+    pub lines: Vec<String>,
+    pub file_name: String,
+    pub file_path: String,
+    pub file_format: FileFormat,
+    // what the tab key inserts and how wide tabs are drawn
+    pub indent: Indent,
+    // whether the indent is the one from the settings, the file had none to
+    // detect and `set` didn't change it
+    pub default_indent: bool,
+    // picked by the file's extension
+    pub language: &'static Language,
+    // set while the buffer is edited as raw bytes
+    pub hex: Option<HexEditor>,
+    pub history: History,
+    // contents as they were last loaded from or written to disk
+    saved_lines: Vec<String>,
+    saved_format: FileFormat,
+    // none until the file exists on disk
+    disk_state: Option<DiskState>,
+}
+
+impl Buffer {
+    pub fn new(file_name: String, file_path: String, lines: Vec<String>) -> Self {
+        Self {
+            language: language_of(&file_name),
+            saved_lines: lines.clone(),
+            saved_format: FileFormat::new(),
+            lines,
+            file_name,
+            file_path,
+            file_format: FileFormat::new(),
+            indent: Indent::spaces(4),
+            default_indent: true,
+            hex: None,
+            history: History::new(),
+            disk_state: None,
+        }
+    }
+
+    // empty buffer for a file that only gets created once it's saved
+    pub fn create(file_path: PathBuf) -> Self {
+        Self::new(
+            file_name_of(&file_path),
+            file_path.display().to_string(),
+            vec![String::new()],
+        )
+    }
+
+    pub fn open(file_path: PathBuf) -> io::Result<Self> {
+        let bytes = fs::read(&file_path)?;
+        let disk_state = DiskState::of(&file_path, &bytes);
+        let file_name = file_name_of(&file_path);
+        let file_path = file_path.display().to_string();
+
+        if encoding::is_binary(&bytes) {
+            let mut buffer = Self::new(file_name, file_path, vec![String::new()]);
+            buffer.hex = Some(HexEditor::new(bytes.clone(), bytes));
+            buffer.disk_state = Some(disk_state);
+            return Ok(buffer);
+        }
+
+        let (lines, file_format) = decode_file(&bytes, None);
+
+        let mut buffer = Self::new(file_name, file_path, lines);
+        buffer.file_format = file_format;
+        buffer.saved_format = file_format;
+        buffer.disk_state = Some(disk_state);
+        Ok(buffer)
+    }
+
+    // reads the file again from disk, decoding it with the given encoding
+    pub fn reopen(&mut self, encoding: Encoding) -> io::Result<()> {
+        let bytes = fs::read(&self.file_path)?;
+        let (lines, file_format) = decode_file(&bytes, Some(encoding));
+
+        self.lines = lines;
+        self.file_format = file_format;
+        self.hex = None;
+        self.mark_saved();
+        self.disk_state = Some(DiskState::of(Path::new(&self.file_path), &bytes));
+        Ok(())
+    }
+
+    // throws away the buffer's contents in favour of what's on disk now
+    pub fn reload(&mut self) -> io::Result<()> {
+        let (indent, default_indent) = (self.indent, self.default_indent);
+        *self = Self::open(PathBuf::from(&self.file_path))?;
+        self.indent = Indent::detect(&self.lines, indent);
+        self.default_indent = default_indent && !Indent::any_indented(&self.lines);
+        Ok(())
+    }
+
+    // the indent of the lines, or the given one when they have none
+    pub fn detect_indent(&mut self, default: Indent) {
+        self.indent = Indent::detect(&self.lines, default);
+        self.default_indent = !Indent::any_indented(&self.lines);
+    }
+
+    // whether someone else wrote to the file since it was opened or saved,
+    // the contents only get compared when the modification time moved
+    pub fn changed_on_disk(&mut self) -> bool {
+        let Some(disk_state) = &mut self.disk_state else {
+            return false;
+        };
+
+        let file_path = Path::new(&self.file_path);
+        let modified = modified_time(file_path);
+        if modified == disk_state.modified {
+            return false;
+        }
+
+        match fs::read(file_path) {
+            Ok(bytes) if hash_of(&bytes) == disk_state.hash => {
+                disk_state.modified = modified;
+                false
+            }
+            Ok(_) => true,
+            // deleted or unreadable, saving puts it back
+            Err(_) => false,
+        }
+    }
+
+    // keeps the buffer as it is, it now counts as modified against the new
+    // version on disk so saving overwrites it
+    pub fn keep_over_disk(&mut self) -> io::Result<()> {
+        let bytes = fs::read(&self.file_path)?;
+        let (lines, file_format) = decode_file(&bytes, Some(self.file_format.encoding));
+
+        self.saved_lines = lines;
+        self.saved_format = file_format;
+        if let Some(hex) = &mut self.hex {
+            hex.set_saved_bytes(bytes.clone());
+        }
+        self.disk_state = Some(DiskState::of(Path::new(&self.file_path), &bytes));
+        Ok(())
+    }
+
+    // the file was deleted, everything in the buffer is unsaved now and
+    // saving creates the file again
+    pub fn forget_disk(&mut self) {
+        self.saved_lines = Vec::new();
+        if let Some(hex) = &mut self.hex {
+            hex.set_saved_bytes(Vec::new());
+        }
+        self.disk_state = None;
+    }
+
+    // the file on disk as lines, decoded like the buffer
+    pub fn disk_lines(&self) -> io::Result<Vec<String>> {
+        let bytes = fs::read(&self.file_path)?;
+        let (lines, _) = decode_file(&bytes, Some(self.file_format.encoding));
+        Ok(lines)
+    }
+
+    // puts recovered contents into the buffer, the file on disk stays the saved state
+    pub fn recover(&mut self, bytes: Vec<u8>) {
+        match &mut self.hex {
+            Some(hex) => hex.bytes = bytes,
+            None => {
+                let (lines, file_format) = decode_file(&bytes, Some(self.file_format.encoding));
+                self.lines = lines;
+                self.file_format = file_format;
+            }
+        }
+    }
+
+    pub fn enter_hex(&mut self) -> io::Result<()> {
+        let bytes = self.to_bytes()?;
+        let saved_bytes = encode_file(&self.saved_lines, &self.saved_format)?;
+
+        self.hex = Some(HexEditor::new(bytes, saved_bytes));
+        Ok(())
+    }
+
+    // goes back to editing text, the bytes get decoded with the file's encoding
+    pub fn leave_hex(&mut self) -> io::Result<()> {
+        if let Some(hex) = &self.hex {
+            if encoding::is_binary(&hex.bytes) {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    "binary files can only be edited as hex",
+                ));
+            }
+
+            let (lines, file_format) = decode_file(&hex.bytes, Some(self.file_format.encoding));
+            self.lines = lines;
+            self.file_format = file_format;
+        }
+
+        self.hex = None;
+        Ok(())
+    }
+
+    pub fn is_modified(&self) -> bool {
+        match &self.hex {
+            Some(hex) => hex.is_modified(),
+            None => self.lines != self.saved_lines || self.file_format != self.saved_format,
+        }
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.saved_lines = self.lines.clone();
+        self.saved_format = self.file_format;
+    }
+
+    // the contents exactly as they end up on disk
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        match &self.hex {
+            Some(hex) => Ok(hex.bytes.clone()),
+            None => encode_file(&self.lines, &self.file_format),
+        }
+    }
+
+    fn write(&mut self, file_path: &Path) -> io::Result<()> {
+        let bytes = self.to_bytes()?;
+        write_atomic(file_path, &bytes)?;
+        self.disk_state = Some(DiskState::of(file_path, &bytes));
+
+        if let Some(hex) = &mut self.hex {
+            hex.mark_saved();
+
+            // what the text looks like on disk now, for when hex mode is left
+            let (lines, file_format) = decode_file(&bytes, Some(self.file_format.encoding));
+            self.saved_lines = lines;
+            self.saved_format = file_format;
+        } else {
+            self.mark_saved();
+        }
+
+        Ok(())
+    }
+
+    pub fn save(&mut self) -> io::Result<()> {
+        if self.file_name.is_empty() {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "no file is open"));
+        }
+        self.write(&PathBuf::from(&self.file_path))
+    }
+
+    // the file got renamed or moved, the buffer follows it
+    pub fn moved(&mut self, file_path: PathBuf) {
+        self.file_name = file_name_of(&file_path);
+        self.file_path = file_path.display().to_string();
+        self.language = language_of(&self.file_name);
+    }
+
+    // saves to a new path, the buffer keeps pointing to it afterwards
+    pub fn save_as(&mut self, file_path: PathBuf) -> io::Result<()> {
+        let file_name = match file_path.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => return Err(io::Error::new(ErrorKind::InvalidInput, "not a file name")),
+        };
+
+        self.write(&file_path)?;
+        self.language = language_of(&file_name);
+        self.file_name = file_name;
+        self.file_path = file_path.display().to_string();
+        Ok(())
+    }
+}
+
+fn file_name_of(file_path: &Path) -> String {
+    match file_path.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => file_path.display().to_string(),
+    }
+}
+
+fn encode_file(lines: &[String], file_format: &FileFormat) -> io::Result<Vec<u8>> {
+    let line_ending = file_format.line_ending.as_str();
+    let mut text = lines.join(line_ending);
+
+    if file_format.final_newline {
+        text.push_str(line_ending);
+    }
+
+    let encoding = file_format.encoding;
+    let mut bytes = Vec::new();
+
+    if file_format.bom {
+        bytes.extend_from_slice(encoding.bom());
+    }
+
+    match encoding.encode(&text) {
+        Ok(encoded) => bytes.extend(encoded),
+        Err(c) => {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("'{}' can't be written as {}", c, encoding.name()),
+            ))
+        }
+    }
+
+    Ok(bytes)
+}
+
+// turns file contents into lines, the encoding is detected unless one is given
+fn decode_file(bytes: &[u8], encoding: Option<Encoding>) -> (Vec<String>, FileFormat) {
+    let (encoding, bom_len) = match encoding {
+        Some(encoding) if bytes.starts_with(encoding.bom()) => (encoding, encoding.bom().len()),
+        Some(encoding) => (encoding, 0),
+        None => encoding::detect(bytes),
+    };
+
+    let contents = encoding.decode(&bytes[bom_len..]);
+    let (lines, mut file_format) = split_text(&contents);
+    file_format.encoding = encoding;
+    file_format.bom = bom_len > 0;
+
+    (lines, file_format)
+}
+
+// splits file contents into lines and remembers how they were written
+fn split_text(contents: &str) -> (Vec<String>, FileFormat) {
+    let mut file_format = FileFormat::new();
+
+    // mixed files get whatever ending most of their lines use
+    let crlf_count = contents.matches("\r\n").count();
+    let lf_count = contents.matches('\n').count() - crlf_count;
+    if crlf_count > lf_count {
+        file_format.line_ending = LineEnding::Crlf;
+    }
+
+    // every ending gets split off, saving writes the chosen one everywhere
+    let mut lines: Vec<String> = contents
+        .split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line).to_string())
+        .collect();
+
+    if lines.len() > 1 && lines.last().unwrap().is_empty() {
+        lines.pop();
+    } else {
+        file_format.final_newline = false;
+    }
+
+    (lines, file_format)
+}
+
+// writes into a temporary file next to the target and renames it over the
+// original, so a crash or a full disk never leaves a half written file behind
+fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    // follow symlinks so the link itself doesn't get replaced by a file
+    let path = fs::canonicalize(path).unwrap_or(path.to_path_buf());
+    let metadata = fs::metadata(&path).ok();
+
+    let temp_path = match path.file_name() {
+        Some(name) => path.with_file_name(format!(".{}.pico-tmp", name.to_string_lossy())),
+        None => return Err(io::Error::new(ErrorKind::InvalidInput, "not a file name")),
+    };
+
+    let result =
+        write_temp(&temp_path, contents, &metadata).and_then(|_| fs::rename(&temp_path, &path));
+
+    match result {
+        Ok(()) => {
+            sync_parent(&path);
+            Ok(())
+        }
+        Err(error) => {
+            let _ = fs::remove_file(&temp_path);
+
+            // the directory isn't writable or the owner can't be kept,
+            // the file itself might still be writable in place
+            if metadata.is_some() && error.kind() == ErrorKind::PermissionDenied {
+                write_in_place(&path, contents)
+            } else {
+                Err(error)
+            }
+        }
+    }
+}
+
+fn write_temp(
+    temp_path: &Path,
+    contents: &[u8],
+    metadata: &Option<fs::Metadata>,
+) -> io::Result<()> {
+    let mut file = File::create(temp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+
+    if let Some(metadata) = metadata {
+        fs::set_permissions(temp_path, metadata.permissions())?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            std::os::unix::fs::chown(temp_path, Some(metadata.uid()), Some(metadata.gid()))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_in_place(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
+// makes the rename itself durable
+fn sync_parent(path: &Path) {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // decodes the bytes and checks that saving them unchanged gives the
+    // same bytes back
+    fn round_trip(bytes: &[u8]) -> (Vec<String>, FileFormat) {
+        let (lines, file_format) = decode_file(bytes, None);
+        assert_eq!(encode_file(&lines, &file_format).unwrap(), bytes);
+        (lines, file_format)
+    }
+
+    #[test]
+    fn round_trips_line_endings() {
+        let (lines, file_format) = round_trip(b"a\nb\n");
+        assert_eq!(lines, ["a", "b"]);
+        assert_eq!(file_format, FileFormat::new());
+
+        let (lines, file_format) = round_trip(b"a\r\n\r\nb\r\n");
+        assert_eq!(lines, ["a", "", "b"]);
+        assert_eq!(file_format.line_ending, LineEnding::Crlf);
+
+        let (lines, file_format) = round_trip(b"a\nb");
+        assert_eq!(lines, ["a", "b"]);
+        assert!(!file_format.final_newline);
+
+        let (lines, file_format) = round_trip(b"");
+        assert_eq!(lines, [""]);
+        assert!(!file_format.final_newline);
+        assert_eq!(round_trip(b"\n").0, [""]);
+    }
+
+    #[test]
+    fn saves_mixed_endings_with_the_most_common_one() {
+        let (lines, file_format) = decode_file(b"a\r\nb\nc\r\n", None);
+        assert_eq!(lines, ["a", "b", "c"]);
+        assert_eq!(file_format.line_ending, LineEnding::Crlf);
+        assert_eq!(
+            encode_file(&lines, &file_format).unwrap(),
+            b"a\r\nb\r\nc\r\n"
+        );
+
+        let (lines, file_format) = decode_file(b"a\nb\r\nc\n", None);
+        assert_eq!(file_format.line_ending, LineEnding::Lf);
+        assert_eq!(encode_file(&lines, &file_format).unwrap(), b"a\nb\nc\n");
+    }
+
+    #[test]
+    fn round_trips_a_bom() {
+        let (lines, file_format) = round_trip(b"\xef\xbb\xbfa\r\n");
+        assert_eq!(lines, ["a"]);
+        assert!(file_format.bom);
+    }
+
+    #[test]
+    fn round_trips_other_encodings() {
+        let (lines, file_format) = round_trip(b"\xff\xfea\0\r\0\n\0\xe9\0");
+        assert_eq!(lines, ["a", "\u{e9}"]);
+        assert_eq!(file_format.encoding, Encoding::Utf16Le);
+        assert!(file_format.bom);
+
+        let (lines, file_format) = round_trip(b"\xfe\xff\0a\0\n");
+        assert_eq!(lines, ["a"]);
+        assert_eq!(file_format.encoding, Encoding::Utf16Be);
+
+        let (_, file_format) = round_trip(b"a\0b\0\n\0c\0d\0\n\0");
+        assert_eq!(file_format.encoding, Encoding::Utf16Le);
+        assert!(!file_format.bom);
+
+        let (lines, file_format) = round_trip(b"caf\xe9 \x80\n");
+        assert_eq!(lines, ["caf\u{e9} \u{20ac}"]);
+        assert_eq!(file_format.encoding, Encoding::Windows1252);
+
+        let (lines, file_format) = round_trip(b"caf\xe9 \x81\n");
+        assert_eq!(lines, ["caf\u{e9} \u{81}"]);
+        assert_eq!(file_format.encoding, Encoding::Latin1);
+    }
+
+    #[test]
+    fn reopens_with_a_given_encoding() {
+        let (lines, file_format) = decode_file(b"caf\xc3\xa9\n", Some(Encoding::Latin1));
+        assert_eq!(lines, ["caf\u{c3}\u{a9}"]);
+        assert_eq!(encode_file(&lines, &file_format).unwrap(), b"caf\xc3\xa9\n");
+    }
+
+    #[test]
+    fn refuses_to_save_chars_the_encoding_lacks() {
+        let mut file_format = FileFormat::new();
+        file_format.encoding = Encoding::Latin1;
+        assert!(encode_file(&[String::from("\u{20ac}")], &file_format).is_err());
+    }
+}
@@ -0,0 +1,67 @@
+use std::fs;
+use std::path::PathBuf;
+
+// older commands get dropped once there are more
+const MAX_ENTRIES: usize = 500;
+
+// commands typed into the console, kept in a file between sessions
+pub struct CommandHistory {
+    // oldest first
+    entries: Vec<String>,
+    // none when there is no data directory to keep the file in
+    path: Option<PathBuf>,
+}
+
+impl CommandHistory {
+    pub fn new() -> Self {
+        let path = dirs::data_dir().map(|dir| dir.join("pico-term").join("console_history"));
+        let entries = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|contents| contents.lines().map(String::from).collect())
+            .unwrap_or_default();
+
+        Self { entries, path }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn get(&self, i: usize) -> Option<&String> {
+        self.entries.get(i)
+    }
+
+    // a command used again moves to the end instead of being kept twice
+    pub fn add(&mut self, command: &str) {
+        let command = command.trim();
+        if command.is_empty() {
+            return;
+        }
+
+        self.entries.retain(|entry| entry != command);
+        self.entries.push(command.to_owned());
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+        self.save();
+    }
+
+    // the newest entry before `before` that contains the query
+    pub fn find(&self, query: &str, before: usize) -> Option<usize> {
+        self.entries[..before.min(self.entries.len())]
+            .iter()
+            .rposition(|entry| entry.contains(query))
+    }
+
+    // losing the history isn't worth bothering anyone with, so errors are ignored
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let _ = fs::write(path, self.entries.join("\n") + "\n");
+    }
+}
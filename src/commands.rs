@@ -0,0 +1,411 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::buffer::{Buffer, LineEnding};
+use crate::encoding::Encoding;
+use crate::functions::describe_error;
+use crate::indent::Indent;
+use crate::language::{find_language, LANGUAGES};
+use crate::project_search::ProjectSearch;
+use crate::replace::Replace;
+use crate::settings::Settings;
+
+// what a command leaves for the editor to do
+pub enum CommandResult {
+    // shown in the status bar
+    Message(String),
+    Replace(Replace),
+    Search(ProjectSearch),
+    // a path saves the buffer as that file
+    Save { path: Option<PathBuf>, quit: bool },
+    // force quits without asking about unsaved changes
+    Quit { force: bool },
+    Open(PathBuf),
+    // line index, starting at 0
+    Goto(usize),
+    // line indexes from and to, both included, and whether they get outdented
+    Indent((usize, usize), bool),
+    Comment((usize, usize)),
+}
+
+// how a command takes what comes after its name
+#[derive(PartialEq)]
+enum Arguments {
+    None,
+    OptionalFile,
+    File,
+    One,
+    Two,
+    // everything after the name as it was typed
+    Raw,
+}
+
+// every command with its usage, also used for completing names
+const COMMANDS: &[(&str, Arguments, &str)] = &[
+    ("w", Arguments::OptionalFile, "w [file]"),
+    ("wq", Arguments::OptionalFile, "wq [file]"),
+    ("q", Arguments::None, "q"),
+    ("q!", Arguments::None, "q!"),
+    ("e", Arguments::File, "e <file>"),
+    ("saveas", Arguments::File, "saveas <file>"),
+    ("goto", Arguments::One, "goto <line>"),
+    ("indent", Arguments::Two, "indent <from> <to>"),
+    ("outdent", Arguments::Two, "outdent <from> <to>"),
+    ("comment", Arguments::Two, "comment <from> <to>"),
+    ("set", Arguments::One, "set <option>=<value>"),
+    ("reopen", Arguments::One, "reopen <encoding>"),
+    (
+        "replace",
+        Arguments::Raw,
+        "replace /pattern/replacement/[gci]",
+    ),
+    (
+        "search",
+        Arguments::Raw,
+        "search <text> or search /regex/[i]",
+    ),
+];
+
+const OPTIONS: &[&str] = &[
+    "lineending",
+    "encoding",
+    "bom",
+    "finalnewline",
+    "indent",
+    "tabwidth",
+    "saveonunfocus",
+    "autosave",
+    "swapinterval",
+    "autoclose",
+    "language",
+];
+
+// runs a line typed into the console, the message ends up in the status bar
+pub fn run_command(
+    input: &str,
+    buffer: &mut Buffer,
+    settings: &mut Settings,
+) -> Result<CommandResult, String> {
+    let input = input.trim();
+    let name = input.split_whitespace().next().unwrap_or("");
+    // the pattern of replace and search can contain spaces and quotes
+    let raw = input[name.len()..].trim_start();
+
+    if name.is_empty() {
+        return Ok(CommandResult::Message(String::new()));
+    }
+    // a line number on its own jumps there
+    if name.chars().all(|c| c.is_ascii_digit()) {
+        return goto(name, buffer);
+    }
+
+    let Some((_, arguments, usage)) = COMMANDS.iter().find(|(known, _, _)| *known == name) else {
+        return Err("Unknown command '".to_owned() + name + "'");
+    };
+
+    let args = match arguments {
+        Arguments::Raw => Vec::new(),
+        _ => split_args(raw)?,
+    };
+    let count_fits = match arguments {
+        Arguments::None => args.is_empty(),
+        Arguments::OptionalFile => args.len() <= 1,
+        Arguments::File | Arguments::One => args.len() == 1,
+        Arguments::Two => args.len() == 2,
+        Arguments::Raw => true,
+    };
+    if !count_fits {
+        return Err("usage: ".to_owned() + usage);
+    }
+
+    let file = args.first().map(|arg| resolve_path(arg)).transpose()?;
+
+    match name {
+        "w" => Ok(CommandResult::Save {
+            path: file,
+            quit: false,
+        }),
+        "wq" => Ok(CommandResult::Save {
+            path: file,
+            quit: true,
+        }),
+        "saveas" => Ok(CommandResult::Save {
+            path: file,
+            quit: false,
+        }),
+        "q" => Ok(CommandResult::Quit { force: false }),
+        "q!" => Ok(CommandResult::Quit { force: true }),
+        "e" => Ok(CommandResult::Open(file.unwrap_or_default())),
+        "goto" => goto(&args[0], buffer),
+        "comment" => line_range(&args[0], &args[1], buffer).map(CommandResult::Comment),
+        "indent" | "outdent" => line_range(&args[0], &args[1], buffer)
+            .map(|lines| CommandResult::Indent(lines, name == "outdent")),
+        "set" => set_option(&args[0], buffer, settings).map(CommandResult::Message),
+        "reopen" => reopen(&args[0], buffer).map(CommandResult::Message),
+        "replace" => Replace::parse(raw).map(CommandResult::Replace),
+        _ => {
+            let pattern = ProjectSearch::parse_pattern(raw)?;
+            let root = env::current_dir()
+                .map_err(|error| "Could not search: ".to_owned() + &describe_error(&error))?;
+            Ok(CommandResult::Search(ProjectSearch::run(&pattern, root)))
+        }
+    }
+}
+
+// completes the word before the cursor, returns the new input and
+// the candidates when there is more than one
+pub fn complete(input: &str) -> (String, Vec<String>) {
+    let (start, word) = match input.rfind(' ') {
+        Some(i) => (&input[..i + 1], &input[i + 1..]),
+        None => ("", input),
+    };
+    let name = input.split_whitespace().next().unwrap_or("");
+
+    let candidates: Vec<String> = if start.trim().is_empty() {
+        COMMANDS
+            .iter()
+            .map(|(name, _, _)| name.to_string())
+            .filter(|name| name.starts_with(word))
+            .collect()
+    } else if name == "set" && word.starts_with("language=") {
+        LANGUAGES
+            .iter()
+            .map(|language| "language=".to_owned() + &language.id())
+            .filter(|option| option.starts_with(word))
+            .collect()
+    } else if name == "set" && start.trim() == "set" {
+        OPTIONS
+            .iter()
+            .map(|option| option.to_string() + "=")
+            .filter(|option| option.starts_with(word))
+            .collect()
+    } else if COMMANDS.iter().any(|(known, arguments, _)| {
+        *known == name && matches!(arguments, Arguments::File | Arguments::OptionalFile)
+    }) {
+        complete_path(word)
+    } else {
+        Vec::new()
+    };
+
+    let completed = match candidates.as_slice() {
+        [] => word.to_owned(),
+        // names get a space behind them, directories already end with a /
+        [only] if start.is_empty() => only.to_owned() + " ",
+        [only] => only.to_owned(),
+        _ => common_prefix(&candidates),
+    };
+
+    let shown = match candidates.len() {
+        1 => Vec::new(),
+        _ => candidates,
+    };
+    (start.to_owned() + &completed, shown)
+}
+
+// files and directories starting with what's typed, relative to the working directory
+fn complete_path(word: &str) -> Vec<String> {
+    let (directory, prefix) = match word.rfind('/') {
+        Some(i) => (&word[..i + 1], &word[i + 1..]),
+        None => ("", word),
+    };
+    let Ok(root) = env::current_dir() else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(root.join(directory)) else {
+        return Vec::new();
+    };
+
+    let mut candidates: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let is_dir = entry.file_type().is_ok_and(|kind| kind.is_dir());
+            Some(directory.to_owned() + &name + if is_dir { "/" } else { "" })
+        })
+        .collect();
+    candidates.sort();
+    candidates
+}
+
+fn common_prefix(words: &[String]) -> String {
+    let mut prefix = words[0].clone();
+    for word in &words[1..] {
+        let len = prefix
+            .chars()
+            .zip(word.chars())
+            .take_while(|(a, b)| a == b)
+            .map(|(c, _)| c.len_utf8())
+            .sum();
+        prefix.truncate(len);
+    }
+    prefix
+}
+
+// splits on spaces, "double" or 'single' quotes keep spaces in one argument
+fn split_args(input: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut current: Option<String> = None;
+    let mut quote: Option<char> = None;
+
+    for c in input.chars() {
+        match (quote, c) {
+            (Some(open), _) if c == open => quote = None,
+            (Some(_), _) => current.get_or_insert_with(String::new).push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                current.get_or_insert_with(String::new);
+            }
+            (None, _) if c.is_whitespace() => args.extend(current.take()),
+            (None, _) => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if quote.is_some() {
+        return Err(String::from("Missing closing quote"));
+    }
+    args.extend(current);
+    Ok(args)
+}
+
+// file arguments are relative to the working directory
+fn resolve_path(arg: &str) -> Result<PathBuf, String> {
+    let root = env::current_dir()
+        .map_err(|error| "Could not find the file: ".to_owned() + &describe_error(&error))?;
+    Ok(root.join(arg))
+}
+
+fn goto(line: &str, buffer: &Buffer) -> Result<CommandResult, String> {
+    match line.parse::<usize>() {
+        Ok(0) | Err(_) => Err(String::from("goto needs a line number starting at 1")),
+        // past the end goes to the last line
+        Ok(line) => Ok(CommandResult::Goto(
+            (line - 1).min(buffer.lines.len().saturating_sub(1)),
+        )),
+    }
+}
+
+// two line numbers starting at 1 in either order, the end is cut to the
+// last line
+fn line_range(from: &str, to: &str, buffer: &Buffer) -> Result<(usize, usize), String> {
+    let last = buffer.lines.len().saturating_sub(1);
+    let parse = |line: &str| match line.parse::<usize>() {
+        Ok(0) | Err(_) => Err(String::from("Line numbers start at 1")),
+        Ok(line) => Ok((line - 1).min(last)),
+    };
+    let (from, to) = (parse(from)?, parse(to)?);
+    Ok((from.min(to), from.max(to)))
+}
+
+fn set_option(
+    option: &str,
+    buffer: &mut Buffer,
+    settings: &mut Settings,
+) -> Result<String, String> {
+    let (name, value) = match option.split_once('=') {
+        Some(pair) => pair,
+        None => return Err("Missing value for '".to_owned() + option + "'"),
+    };
+
+    match name {
+        "lineending" => {
+            buffer.file_format.line_ending = match value {
+                "lf" => LineEnding::Lf,
+                "crlf" => LineEnding::Crlf,
+                _ => return Err(String::from("lineending has to be lf or crlf")),
+            }
+        }
+        "encoding" => {
+            let encoding = parse_encoding(value)?;
+            buffer.file_format.encoding = encoding;
+            buffer.file_format.bom &= !encoding.bom().is_empty();
+        }
+        "bom" => {
+            let bom = parse_switch(name, value)?;
+            if bom && buffer.file_format.encoding.bom().is_empty() {
+                return Err(buffer.file_format.encoding.name().to_owned() + " has no BOM");
+            }
+            buffer.file_format.bom = bom;
+        }
+        "finalnewline" => buffer.file_format.final_newline = parse_switch(name, value)?,
+        "indent" => {
+            buffer.indent = match value {
+                "tabs" => Indent::tabs(buffer.indent.width),
+                _ => Indent::spaces(parse_width(name, value)?),
+            };
+            buffer.default_indent = false;
+        }
+        "tabwidth" => {
+            buffer.indent.width = parse_width(name, value)?;
+            buffer.default_indent = false;
+        }
+        "saveonunfocus" => settings.save_on_unfocus = parse_switch(name, value)?,
+        "autosave" => settings.autosave_after = parse_seconds(name, value)?,
+        "swapinterval" => settings.swap_interval = parse_seconds(name, value)?,
+        "autoclose" => settings.auto_close = parse_switch(name, value)?,
+        "language" => match find_language(value) {
+            Some(language) => buffer.language = language,
+            None => {
+                let ids: Vec<String> = LANGUAGES.iter().map(|language| language.id()).collect();
+                return Err(format!(
+                    "Unknown language '{}', there are {}",
+                    value,
+                    ids.join(", ")
+                ));
+            }
+        },
+        _ => return Err("Unknown option '".to_owned() + name + "'"),
+    }
+
+    Ok(name.to_owned() + " set to " + value)
+}
+
+fn reopen(name: &str, buffer: &mut Buffer) -> Result<String, String> {
+    let encoding = parse_encoding(name)?;
+
+    if buffer.file_name.is_empty() {
+        return Err(String::from("No file is open"));
+    }
+    if buffer.is_modified() {
+        return Err(String::from(
+            "Save or discard your changes before reopening",
+        ));
+    }
+
+    match buffer.reopen(encoding) {
+        Ok(()) => Ok("Reopened as ".to_owned() + encoding.name()),
+        Err(error) => Err("Could not reopen: ".to_owned() + &describe_error(&error)),
+    }
+}
+
+fn parse_encoding(name: &str) -> Result<Encoding, String> {
+    match Encoding::from_name(name) {
+        Some(encoding) => Ok(encoding),
+        None => Err("Unknown encoding '".to_owned() + name + "'"),
+    }
+}
+
+fn parse_seconds(name: &str, value: &str) -> Result<u64, String> {
+    match value.parse() {
+        Ok(seconds) => Ok(seconds),
+        Err(_) => Err(name.to_owned() + " has to be a number of seconds"),
+    }
+}
+
+fn parse_width(name: &str, value: &str) -> Result<usize, String> {
+    match value.parse() {
+        Ok(width @ 1..=16) => Ok(width),
+        _ => Err(name.to_owned() + " has to be tabs or a width from 1 to 16"),
+    }
+}
+
+fn parse_switch(name: &str, value: &str) -> Result<bool, String> {
+    match value {
+        "on" | "true" => Ok(true),
+        "off" | "false" => Ok(false),
+        _ => Err(name.to_owned() + " has to be on or off"),
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use serde::Deserialize;
+
+use crate::functions::modified_time;
+use crate::keymap::{self, Keymap};
+use crate::settings::Settings;
+use crate::theme::{self, Theme, THEMES};
+
+// read after the user's file, so a project can override it
+const PROJECT_FILE: &str = ".pico-term.toml";
+
+// every setting is optional, missing ones keep their default
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    editor: EditorSection,
+    colors: ColorSection,
+    keys: KeysSection,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct EditorSection {
+    tab_width: Option<usize>,
+    use_tabs: Option<bool>,
+    scroll_step: Option<usize>,
+    line_number_width: Option<usize>,
+    save_on_unfocus: Option<bool>,
+    autosave: Option<u64>,
+    swap_interval: Option<u64>,
+    auto_close: Option<bool>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ColorSection {
+    theme: Option<String>,
+    // "#rrggbb", replace the theme's colors
+    background: Option<String>,
+    panel: Option<String>,
+}
+
+// a preset to start from and key bindings per scope, like
+// "ctrl+s" = "save" in [keys.editor]
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct KeysSection {
+    preset: Option<String>,
+    editor: BTreeMap<String, String>,
+    text: BTreeMap<String, String>,
+    write: BTreeMap<String, String>,
+    edit: BTreeMap<String, String>,
+    hex: BTreeMap<String, String>,
+}
+
+// the config files and when they were last read, to notice edits
+pub struct Config {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+    // what the files set the last time, a reload only applies what changed
+    // since so the Color menu and `set` keep working
+    loaded: Option<(Settings, Theme)>,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        let mut files = Vec::new();
+        if let Some(dir) = dirs::config_dir() {
+            files.push((dir.join("pico-term").join("config.toml"), None));
+        }
+        files.push((env::current_dir().unwrap().join(PROJECT_FILE), None));
+
+        Self {
+            files,
+            loaded: None,
+        }
+    }
+
+    // reads all files into fresh settings and keys, missing files are fine
+    // and a broken one doesn't stop the others from being used. settings
+    // changed while running are kept unless the files change them too
+    pub fn load(&mut self, settings: &mut Settings, keymap: &mut Keymap) -> Vec<String> {
+        let mut files = Vec::new();
+        let mut errors = Vec::new();
+
+        for (path, modified) in &mut self.files {
+            *modified = modified_time(path);
+            let Ok(contents) = fs::read_to_string(&*path) else {
+                continue;
+            };
+
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            match toml::from_str::<ConfigFile>(&contents) {
+                Ok(file) => files.push((name.into_owned(), file)),
+                Err(error) => errors.push(describe_parse_error(&name, &contents, &error)),
+            }
+        }
+
+        let mut loaded = Settings::new();
+        let mut theme = THEMES[0];
+        let mut keys = Keymap::new();
+
+        // the project's preset wins, its bindings go on top of the user's
+        if let Some((name, preset)) = files
+            .iter()
+            .rev()
+            .find_map(|(name, file)| Some((name, file.keys.preset.as_ref()?)))
+        {
+            match Keymap::preset(preset) {
+                Some(preset) => keys = preset,
+                None => errors.push(format!(
+                    "{}: unknown key preset '{}', there are {}",
+                    name,
+                    preset,
+                    keymap::preset_names().join(", ")
+                )),
+            }
+        }
+
+        for (name, file) in files {
+            let problems = apply(file.editor, file.colors, &mut loaded, &mut theme)
+                .into_iter()
+                .chain(bind_keys(&file.keys, &mut keys));
+            for problem in problems {
+                errors.push(format!("{}: {}", name, problem));
+            }
+        }
+        errors.extend(
+            keys.conflicts()
+                .into_iter()
+                .map(|conflict| "key conflict: ".to_owned() + &conflict),
+        );
+
+        match self.loaded.replace((loaded.clone(), theme)) {
+            Some((previous, previous_theme)) => {
+                apply_changed(settings, &previous, &loaded);
+                if theme != previous_theme {
+                    theme::set_theme(theme);
+                }
+            }
+            None => {
+                *settings = loaded;
+                theme::set_theme(theme);
+            }
+        }
+        *keymap = keys;
+        errors
+    }
+
+    // whether a file was written, created or deleted since it was read
+    pub fn changed(&self) -> bool {
+        self.files
+            .iter()
+            .any(|(path, modified)| modified_time(path) != *modified)
+    }
+}
+
+fn apply(
+    editor: EditorSection,
+    colors: ColorSection,
+    settings: &mut Settings,
+    theme: &mut Theme,
+) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    let mut check = |name: &str, value: Option<usize>, range: RangeInclusive<usize>| {
+        let value = value?;
+        if range.contains(&value) {
+            return Some(value);
+        }
+        errors.push(format!(
+            "{} has to be between {} and {}",
+            name,
+            range.start(),
+            range.end()
+        ));
+        None
+    };
+
+    if let Some(value) = check("tab_width", editor.tab_width, 1..=16) {
+        settings.tab_width = value;
+    }
+    if let Some(value) = check("scroll_step", editor.scroll_step, 1..=100) {
+        settings.scroll_step = value;
+    }
+    if let Some(value) = check("line_number_width", editor.line_number_width, 1..=9) {
+        settings.line_number_width = value;
+    }
+    if let Some(value) = editor.use_tabs {
+        settings.use_tabs = value;
+    }
+    if let Some(value) = editor.save_on_unfocus {
+        settings.save_on_unfocus = value;
+    }
+    if let Some(value) = editor.autosave {
+        settings.autosave_after = value;
+    }
+    if let Some(value) = editor.swap_interval {
+        settings.swap_interval = value;
+    }
+    if let Some(value) = editor.auto_close {
+        settings.auto_close = value;
+    }
+
+    if let Some(name) = colors.theme {
+        match theme::find_theme(&name) {
+            Some(found) => *theme = found,
+            None => errors.push(format!("unknown theme '{}'", name)),
+        }
+    }
+    for (name, value, color) in [
+        ("background", colors.background, &mut theme.main),
+        ("panel", colors.panel, &mut theme.secondary),
+    ] {
+        let Some(value) = value else {
+            continue;
+        };
+        match parse_color(&value) {
+            Some(parsed) => *color = parsed,
+            None => errors.push(format!("{} has to look like #rrggbb", name)),
+        }
+    }
+
+    errors
+}
+
+// copies the settings whose value in the files differs from `previous`
+fn apply_changed(settings: &mut Settings, previous: &Settings, loaded: &Settings) {
+    macro_rules! changed {
+        ($($field:ident),*) => {
+            $(
+                if loaded.$field != previous.$field {
+                    settings.$field = loaded.$field;
+                }
+            )*
+        };
+    }
+
+    changed!(
+        tab_width,
+        use_tabs,
+        scroll_step,
+        line_number_width,
+        save_on_unfocus,
+        autosave_after,
+        swap_interval,
+        auto_close
+    );
+}
+
+fn bind_keys(keys: &KeysSection, keymap: &mut Keymap) -> Vec<String> {
+    let scopes = [
+        ("editor", &keys.editor),
+        ("text", &keys.text),
+        ("write", &keys.write),
+        ("edit", &keys.edit),
+        ("hex", &keys.hex),
+    ];
+
+    let mut errors = Vec::new();
+    for (scope, bindings) in scopes {
+        for (keys, action) in bindings {
+            if let Err(error) = keymap.bind(scope, keys, action) {
+                errors.push(error);
+            }
+        }
+    }
+    errors
+}
+
+fn parse_color(text: &str) -> Option<(u8, u8, u8)> {
+    let hex = text.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+// "config.toml line 3: unknown field `tabwidth`, expected ..."
+fn describe_parse_error(name: &str, contents: &str, error: &toml::de::Error) -> String {
+    match error.span() {
+        Some(span) => {
+            let line = contents[..span.start].matches('\n').count() + 1;
+            format!("{} line {}: {}", name, line, error.message())
+        }
+        None => format!("{}: {}", name, error.message()),
+    }
+}
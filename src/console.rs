@@ -1,4 +1,5 @@
 use std::io::{self, Error, ErrorKind, Write};
+use std::path::PathBuf;
 
 use crossterm::cursor::MoveTo;
 use crossterm::event::{read, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
@@ -7,8 +8,18 @@ use crossterm::execute;
 use crossterm::style::Stylize;
 use crossterm::terminal::{size, Clear, ClearType};
 
+use crate::command_history::CommandHistory;
+use crate::functions::byte_index;
+use crate::{commands, on_main, Mode};
+
+#[derive(Clone)]
 pub enum ConsoleAction {
     SaveAs,
+    Command,
+    // the directory the explorer creates the new entry in
+    NewFile(PathBuf),
+    NewDirectory(PathBuf),
+    Rename(PathBuf),
 }
 
 pub struct Console {
@@ -17,6 +28,14 @@ pub struct Console {
     current_char: usize,
     submitted: bool,
     action: ConsoleAction,
+    // completions shown behind the input after TAB found several
+    hint: String,
+    history: CommandHistory,
+    // the history entry shown after UP / DOWN, and what was typed before
+    browsing: Option<usize>,
+    draft: String,
+    // the query of a CTRL+R search and the entry it found
+    search: Option<(String, Option<usize>)>,
 }
 
 impl Console {
@@ -27,6 +46,11 @@ impl Console {
             current_char: 0,
             submitted: false,
             action: ConsoleAction::SaveAs,
+            hint: String::new(),
+            history: CommandHistory::new(),
+            browsing: None,
+            draft: String::new(),
+            search: None,
         }
     }
 
@@ -34,34 +58,221 @@ impl Console {
         return &self.action;
     }
 
-    pub fn draw(&mut self) {
-        println!("{}", self.prompt.clone().red());
-        print!("{}", "CONSOLE />".red());
+    pub fn open(&mut self, action: ConsoleAction) {
+        self.open_with(action, "");
+    }
+
+    // the command line with the start of a command already typed in
+    pub fn open_command(&mut self, input: &str) {
+        self.open(ConsoleAction::Command);
+        self.input = input.to_owned();
+        self.current_char = input.chars().count();
+    }
+
+    // asks for something with a label in front of the input
+    pub fn open_with(&mut self, action: ConsoleAction, prompt: &str) {
+        self.action = action;
+        self.prompt = prompt.to_owned();
+        self.input.clear();
+        self.current_char = 0;
+        self.hint.clear();
+        self.browsing = None;
+        self.search = None;
+    }
+
+    // the explorer asked for the input, so it gets the focus back
+    pub fn get_return_mode(&self) -> Mode {
+        match self.action {
+            ConsoleAction::SaveAs | ConsoleAction::Command => Mode::EditMode,
+            _ => Mode::ExplorerMode,
+        }
+    }
+
+    // ESC ends a history search instead of closing the console
+    pub fn is_searching(&self) -> bool {
+        self.search.is_some()
+    }
+
+    pub fn draw(&mut self, width: &usize) {
+        if let Some((query, found)) = &self.search {
+            let prefix = "HISTORY /> ";
+            let found = match found.and_then(|i| self.history.get(i)) {
+                Some(command) => "  ".to_owned() + command,
+                None if query.is_empty() => String::from("  type to search older commands"),
+                None => String::from("  no match"),
+            };
+            let found: String = found
+                .chars()
+                .take(width.saturating_sub(prefix.len() + query.chars().count() + 1))
+                .collect();
+
+            print!("{}", prefix.red());
+            print!("{}", on_main(query));
+            print!("{}", ' '.on_red());
+            print!("{}", on_main(&found).dark_grey());
+
+            let written = prefix.len() + query.chars().count() + 1 + found.chars().count();
+            print!(
+                "{}",
+                on_main(&str::repeat(" ", width.saturating_sub(written)))
+            );
+            return;
+        }
+
+        let input: Vec<char> = self.input.chars().collect();
+        let prefix = "CONSOLE /> ";
+
+        print!("{}", self.prompt.clone().red());
+        print!("{}", prefix.red());
+
+        let before: String = input[0..self.current_char].iter().collect();
+        print!("{}", on_main(&before));
+
+        let mut written = self.prompt.chars().count() + prefix.len() + self.current_char + 1;
+        if self.current_char < input.len() {
+            let after: String = input[self.current_char + 1..].iter().collect();
+            print!("{}", input[self.current_char].on_red());
+            print!("{}", on_main(&after));
+            written += input.len() - self.current_char - 1;
+        } else {
+            print!("{}", ' '.on_red());
+        }
+
+        let hint: String = format!("  {}", self.hint)
+            .chars()
+            .take(width.saturating_sub(written))
+            .collect();
+        print!("{}", on_main(&hint).dark_grey());
+        written += hint.chars().count();
 
-        print!("{}", &self.input[0..self.current_char]);
-        print!(
-            "{}",
-            &self.input[self.current_char..self.current_char + 1].on_red()
-        );
         print!(
             "{}",
-            &self.input[self.current_char + 1..self.input.len() - 1]
+            on_main(&str::repeat(" ", width.saturating_sub(written)))
         );
     }
 
     pub fn submit(&mut self) -> String {
+        self.accept_search();
         let result = self.input.clone();
         self.input.clear();
         self.current_char = 0;
+        self.browsing = None;
+
+        if matches!(self.action, ConsoleAction::Command) {
+            self.history.add(&result);
+        }
         return result;
     }
 
+    // the command a history search found becomes the input
+    fn accept_search(&mut self) {
+        if let Some((_, found)) = self.search.take() {
+            if let Some(command) = found.and_then(|i| self.history.get(i)) {
+                self.input = command.clone();
+                self.current_char = self.input.chars().count();
+            }
+        }
+    }
+
+    // returns true when the key was used by the search
+    fn handle_search_key(&mut self, key_event: KeyEvent) -> bool {
+        let Some((query, found)) = &mut self.search else {
+            return false;
+        };
+        let newest = self.history.len();
+
+        match key_event.code {
+            // again for the next older match
+            KeyCode::Char('r') if key_event.modifiers == KeyModifiers::CONTROL => {
+                let before = found.unwrap_or(newest);
+                *found = self.history.find(query, before).or(*found);
+            }
+            KeyCode::Char(c) => {
+                query.push(c);
+                *found = self.history.find(query, newest);
+            }
+            KeyCode::Backspace => {
+                query.pop();
+                *found = self.history.find(query, newest);
+            }
+            KeyCode::Esc => self.search = None,
+            // any other key takes the match and is handled as usual
+            _ => {
+                self.accept_search();
+                return false;
+            }
+        }
+        true
+    }
+
     pub fn handle_key_event(&mut self, key_event: KeyEvent) {
+        let len = self.input.chars().count();
+        self.hint.clear();
+
+        if self.handle_search_key(key_event) {
+            return;
+        }
+        let is_command = matches!(self.action, ConsoleAction::Command);
+
         match key_event.code {
-            KeyCode::Char(c) => self.input.push(c),
+            KeyCode::Char('r') if key_event.modifiers == KeyModifiers::CONTROL && is_command => {
+                self.search = Some((String::new(), None));
+            }
+            KeyCode::Up if is_command && self.history.len() > 0 => {
+                let i = match self.browsing {
+                    None => {
+                        self.draft = self.input.clone();
+                        self.history.len() - 1
+                    }
+                    Some(i) => i.saturating_sub(1),
+                };
+                self.show_entry(Some(i));
+            }
+            KeyCode::Down => match self.browsing {
+                Some(i) if i + 1 < self.history.len() => self.show_entry(Some(i + 1)),
+                // past the newest entry is what was typed before
+                Some(_) => self.show_entry(None),
+                None => {}
+            },
+            KeyCode::Char(c) => {
+                self.input
+                    .insert(byte_index(&self.input, self.current_char), c);
+                self.current_char += 1;
+            }
+            KeyCode::Backspace if self.current_char > 0 => {
+                self.current_char -= 1;
+                self.input
+                    .remove(byte_index(&self.input, self.current_char));
+            }
+            KeyCode::Delete if self.current_char < len => {
+                self.input
+                    .remove(byte_index(&self.input, self.current_char));
+            }
+            KeyCode::Left => self.current_char = self.current_char.saturating_sub(1),
+            KeyCode::Right => self.current_char = (self.current_char + 1).min(len),
+            KeyCode::Home => self.current_char = 0,
+            KeyCode::End => self.current_char = len,
+            // only commands get completed, the rest of the line stays as it is
+            KeyCode::Tab if matches!(self.action, ConsoleAction::Command) => {
+                let index = byte_index(&self.input, self.current_char);
+                let (completed, candidates) = commands::complete(&self.input[..index]);
+
+                self.current_char = completed.chars().count();
+                self.input = completed + &self.input[index..];
+                self.hint = candidates.join(" ");
+            }
             _ => {}
         }
     }
+
+    fn show_entry(&mut self, i: Option<usize>) {
+        self.browsing = i;
+        self.input = match i.and_then(|i| self.history.get(i)) {
+            Some(command) => command.clone(),
+            None => self.draft.clone(),
+        };
+        self.current_char = self.input.chars().count();
+    }
 }
 
 fn clear_all() -> io::Result<()> {
@@ -0,0 +1,99 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::style::Stylize;
+use similar::{ChangeTag, TextDiff};
+
+use crate::on_secondary;
+
+// read only view of the differences between the file on disk and the buffer
+pub struct DiffView {
+    title: String,
+    // first column is '+', '-', ' ' or '~' for the gap between hunks
+    rows: Vec<(char, String)>,
+    scroll: usize,
+}
+
+impl DiffView {
+    pub fn new() -> Self {
+        Self {
+            title: String::new(),
+            rows: Vec::new(),
+            scroll: 0,
+        }
+    }
+
+    pub fn open(&mut self, title: String, disk_lines: &[String], buffer_lines: &[String]) {
+        let old: Vec<&str> = disk_lines.iter().map(|line| line.as_str()).collect();
+        let new: Vec<&str> = buffer_lines.iter().map(|line| line.as_str()).collect();
+        let diff = TextDiff::from_slices(&old, &new);
+
+        self.title = title;
+        self.rows = Vec::new();
+        self.scroll = 0;
+
+        for (i, group) in diff.grouped_ops(3).iter().enumerate() {
+            if i > 0 {
+                self.rows.push(('~', String::new()));
+            }
+            for op in group {
+                for change in diff.iter_changes(op) {
+                    let sign = match change.tag() {
+                        ChangeTag::Delete => '-',
+                        ChangeTag::Insert => '+',
+                        ChangeTag::Equal => ' ',
+                    };
+                    self.rows.push((sign, change.value().to_string()));
+                }
+            }
+        }
+    }
+
+    // returns true once the view should be closed
+    pub fn handle_key_event(&mut self, key_event: KeyEvent, editor_height: &usize) -> bool {
+        let last = self.rows.len().saturating_sub(*editor_height);
+
+        match key_event.code {
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => return true,
+            KeyCode::Up => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::Down => self.scroll = (self.scroll + 1).min(last),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(*editor_height),
+            KeyCode::PageDown => self.scroll = (self.scroll + editor_height).min(last),
+            KeyCode::Home => self.scroll = 0,
+            KeyCode::End => self.scroll = last,
+            _ => {}
+        }
+
+        false
+    }
+
+    pub fn scroll(&mut self, rows: isize, editor_height: &usize) {
+        let last = self.rows.len().saturating_sub(*editor_height);
+        self.scroll = (self.scroll as isize + rows).clamp(0, last as isize) as usize;
+    }
+
+    pub fn draw(&self, width: &usize, editor_height: &usize) {
+        println!();
+        print!("{}", on_secondary(" "));
+        print!("{}", on_secondary(&self.title));
+        println!("{}", on_secondary("  "));
+        println!("{}", on_secondary(&str::repeat(" ", *width)));
+
+        for i in self.scroll..self.scroll + editor_height {
+            let text = match self.rows.get(i) {
+                Some(('~', _)) => String::from("  ⋯"),
+                Some((sign, line)) => format!(" {} {}", sign, line),
+                None => String::new(),
+            };
+            let text: String = text.chars().take(*width).collect();
+            let padding = str::repeat(" ", width.saturating_sub(text.chars().count()));
+
+            match self.rows.get(i) {
+                Some(('+', _)) => print!("{}", on_secondary(&text).green()),
+                Some(('-', _)) => print!("{}", on_secondary(&text).red()),
+                Some(('~', _)) => print!("{}", on_secondary(&text).dark_grey()),
+                _ => print!("{}", on_secondary(&text)),
+            }
+            print!("{}", on_secondary(&padding));
+            println!();
+        }
+    }
+}
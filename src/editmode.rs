@@ -1,108 +1,100 @@
 use std::io;
 
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crate::{
+    actions::Action,
+    functions::{char_count, clear},
+    indent::Indent,
+    move_down, move_left, move_right, move_up, Mode,
+};
 
-use crate::{functions::clear, move_down, move_left, move_right, move_up, ChangedLineType, Mode};
-
-pub fn handle_key_event(
-    key_event: KeyEvent,
+// the actions that move the cursor and lines around, keymap.rs binds them
+// to keys in edit mode
+pub fn run_action(
+    action: Action,
     current_line: &mut usize,
     current_char: &mut usize,
     current_scroll: &mut usize,
     editor_height: &usize,
     current_mode: &mut Mode,
     lines: &mut Vec<String>,
-) -> io::Result<ChangedLineType> {
-    let mut changed_line = true;
-    match key_event.code {
-        KeyCode::Char(c) => match c {
-            'i' => match key_event.modifiers {
-                KeyModifiers::ALT => {
-                    if *current_line > 0 {
-                        let cursor_line = lines[*current_line].clone();
-                        let next_line = lines[*current_line - 1].clone();
-
-                        lines[*current_line] = next_line;
-                        lines[*current_line - 1] = cursor_line;
-                        *current_line -= 1;
-
-                        clear()?;
-                    }
-                }
-                _ => {
-                    move_up(
-                        current_line,
-                        current_char,
-                        current_scroll,
-                        editor_height,
-                        &lines,
-                    )?;
-                    ()
-                }
-            },
-            'k' => match key_event.modifiers {
-                KeyModifiers::ALT => {
-                    if *current_line < lines.len() - 1 {
-                        let cursor_line = lines[*current_line].clone();
-                        let next_line = lines[*current_line + 1].clone();
-
-                        lines[*current_line] = next_line;
-                        lines[*current_line + 1] = cursor_line;
-                        *current_line += 1;
-
-                        clear()?;
-                    }
-                }
-
-                _ => {
-                    move_down(
-                        current_line,
-                        current_char,
-                        current_scroll,
-                        editor_height,
-                        &lines,
-                    )?;
-                    ()
-                }
-            },
-            'l' => {
-                let whole_word: bool = match key_event.modifiers {
-                    KeyModifiers::ALT => true,
-                    _ => false,
-                };
-
-                changed_line = true;
-
-                move_right(&mut *current_char, &*current_line, &lines, whole_word)?;
-            }
-
-            'j' => {
-                move_left(&mut *current_char, &*current_line, lines, false)?;
-                ()
-            }
+    indent: Indent,
+) -> io::Result<()> {
+    match action {
+        Action::MoveLineUp => {
+            if *current_line > 0 {
+                lines.swap(*current_line, *current_line - 1);
+                *current_line -= 1;
 
-            'u' => {
-                *current_char = 0;
-                clear()?;
-            }
-            'o' => {
-                *current_char = lines[*current_line].len();
                 clear()?;
             }
+        }
+        Action::MoveLineDown => {
+            if *current_line < lines.len() - 1 {
+                lines.swap(*current_line, *current_line + 1);
+                *current_line += 1;
 
-            'd' => {
-                lines.remove(*current_line);
-                *current_char = 0;
-            }
-
-            'q' => {
-                *current_mode = Mode::WriteMode;
                 clear()?;
             }
-            _ => changed_line = false,
-        },
+        }
+        Action::CursorUp => {
+            move_up(
+                current_line,
+                current_char,
+                current_scroll,
+                editor_height,
+                lines,
+            )?;
+        }
+        Action::CursorDown => {
+            move_down(
+                current_line,
+                current_char,
+                current_scroll,
+                editor_height,
+                lines,
+            )?;
+        }
+        Action::CursorRight | Action::NextWord => {
+            let whole_word = action == Action::NextWord;
+            move_right(current_char, current_line, lines, whole_word)?;
+        }
+        Action::CursorLeft | Action::PreviousWord => {
+            let whole_word = action == Action::PreviousWord;
+            move_left(current_char, current_line, lines, whole_word)?;
+        }
+        Action::LineStart => {
+            *current_char = 0;
+            clear()?;
+        }
+        Action::LineEnd => {
+            *current_char = char_count(&lines[*current_line]);
+            clear()?;
+        }
+        Action::DeleteLine => {
+            lines.remove(*current_line);
+            // there is always at least one line
+            if lines.is_empty() {
+                lines.push(String::new());
+            }
+            *current_line = (*current_line).min(lines.len() - 1);
+            *current_char = 0;
+        }
+        // empty lines stay empty instead of getting trailing whitespace
+        Action::IndentLine => {
+            *current_char += indent.indent_line(&mut lines[*current_line]);
+            clear()?;
+        }
+        Action::OutdentLine => {
+            let removed = indent.outdent_line(&mut lines[*current_line]);
+            *current_char = current_char.saturating_sub(removed);
+            clear()?;
+        }
+        Action::WriteMode => {
+            *current_mode = Mode::WriteMode;
+            clear()?;
+        }
         _ => {}
     }
 
-    Ok(ChangedLineType::All)
+    Ok(())
 }
@@ -0,0 +1,248 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+    Windows1252,
+}
+
+// windows-1252 only differs from latin-1 in 0x80..=0x9f, holes stay control characters
+const WINDOWS_1252: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ',
+];
+
+impl Encoding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Latin1 => "Latin-1",
+            Encoding::Windows1252 => "Windows-1252",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().replace(['-', '_'], "").as_str() {
+            "utf8" => Some(Encoding::Utf8),
+            "utf16" | "utf16le" => Some(Encoding::Utf16Le),
+            "utf16be" => Some(Encoding::Utf16Be),
+            "latin1" | "iso88591" => Some(Encoding::Latin1),
+            "windows1252" | "cp1252" => Some(Encoding::Windows1252),
+            _ => None,
+        }
+    }
+
+    pub fn bom(&self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 => &[0xef, 0xbb, 0xbf],
+            Encoding::Utf16Le => &[0xff, 0xfe],
+            Encoding::Utf16Be => &[0xfe, 0xff],
+            Encoding::Latin1 | Encoding::Windows1252 => &[],
+        }
+    }
+
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Utf8 => String::from_utf8_lossy(bytes).to_string(),
+            Encoding::Utf16Le => decode_utf16(bytes, u16::from_le_bytes),
+            Encoding::Utf16Be => decode_utf16(bytes, u16::from_be_bytes),
+            Encoding::Latin1 => bytes.iter().map(|byte| *byte as char).collect(),
+            Encoding::Windows1252 => bytes
+                .iter()
+                .map(|byte| match byte {
+                    0x80..=0x9f => WINDOWS_1252[*byte as usize - 0x80],
+                    _ => *byte as char,
+                })
+                .collect(),
+        }
+    }
+
+    // fails with the first character the encoding can't represent
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>, char> {
+        match self {
+            Encoding::Utf8 => Ok(text.as_bytes().to_vec()),
+            Encoding::Utf16Le => Ok(text.encode_utf16().flat_map(u16::to_le_bytes).collect()),
+            Encoding::Utf16Be => Ok(text.encode_utf16().flat_map(u16::to_be_bytes).collect()),
+            Encoding::Latin1 => text
+                .chars()
+                .map(|c| {
+                    if (c as u32) < 0x100 {
+                        Ok(c as u8)
+                    } else {
+                        Err(c)
+                    }
+                })
+                .collect(),
+            Encoding::Windows1252 => text
+                .chars()
+                .map(
+                    |c| match WINDOWS_1252.iter().position(|special| *special == c) {
+                        Some(index) => Ok(0x80 + index as u8),
+                        None if (c as u32) < 0x80 || (0xa0..0x100).contains(&(c as u32)) => {
+                            Ok(c as u8)
+                        }
+                        None => Err(c),
+                    },
+                )
+                .collect(),
+        }
+    }
+}
+
+// guesses the encoding of file contents, returns it together with the
+// length of the byte order mark that was found
+pub fn detect(bytes: &[u8]) -> (Encoding, usize) {
+    for encoding in [Encoding::Utf8, Encoding::Utf16Le, Encoding::Utf16Be] {
+        if bytes.starts_with(encoding.bom()) {
+            return (encoding, encoding.bom().len());
+        }
+    }
+
+    // utf-16 without a bom, mostly ascii text leaves every other byte empty
+    let pairs = bytes.len() / 2;
+    if pairs > 0 {
+        let even_zeros = bytes.iter().step_by(2).filter(|byte| **byte == 0).count();
+        let odd_zeros = bytes
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .filter(|byte| **byte == 0)
+            .count();
+
+        // a tenth of a short file rounds down to no zeros at all
+        if odd_zeros > pairs / 2 && even_zeros <= pairs / 10 {
+            return (Encoding::Utf16Le, 0);
+        }
+        if even_zeros > pairs / 2 && odd_zeros <= pairs / 10 {
+            return (Encoding::Utf16Be, 0);
+        }
+    }
+
+    if std::str::from_utf8(bytes).is_ok() {
+        return (Encoding::Utf8, 0);
+    }
+
+    // bytes windows-1252 leaves undefined point to plain latin-1
+    if bytes
+        .iter()
+        .any(|byte| matches!(byte, 0x81 | 0x8d | 0x8f | 0x90 | 0x9d))
+    {
+        return (Encoding::Latin1, 0);
+    }
+
+    (Encoding::Windows1252, 0)
+}
+
+// text files don't contain nul bytes or lots of control characters
+pub fn is_binary(bytes: &[u8]) -> bool {
+    let (encoding, _) = detect(bytes);
+    if matches!(encoding, Encoding::Utf16Le | Encoding::Utf16Be) {
+        return false;
+    }
+
+    let sample = &bytes[..bytes.len().min(8192)];
+    if sample.contains(&0) {
+        return true;
+    }
+
+    let control = sample
+        .iter()
+        .filter(|byte| **byte < 0x20 && !matches!(byte, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b))
+        .count();
+
+    control * 10 > sample.len()
+}
+
+fn decode_utf16(bytes: &[u8], to_unit: fn([u8; 2]) -> u16) -> String {
+    let units = bytes.chunks(2).map(|pair| match pair {
+        [first, second] => to_unit([*first, *second]),
+        _ => 0xfffd,
+    });
+
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Encoding; 5] = [
+        Encoding::Utf8,
+        Encoding::Utf16Le,
+        Encoding::Utf16Be,
+        Encoding::Latin1,
+        Encoding::Windows1252,
+    ];
+
+    #[test]
+    fn round_trips_text_in_every_encoding() {
+        for encoding in ALL {
+            let text = "caf\u{e9} na\u{ef}ve\r\n\tend";
+            let bytes = encoding.encode(text).unwrap();
+            assert_eq!(encoding.decode(&bytes), text, "{}", encoding.name());
+        }
+
+        let emoji = "a \u{1f600}";
+        for encoding in [Encoding::Utf8, Encoding::Utf16Le, Encoding::Utf16Be] {
+            assert_eq!(encoding.decode(&encoding.encode(emoji).unwrap()), emoji);
+        }
+    }
+
+    #[test]
+    fn round_trips_every_single_byte() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        for encoding in [Encoding::Latin1, Encoding::Windows1252] {
+            let text = encoding.decode(&bytes);
+            assert_eq!(
+                encoding.encode(&text).unwrap(),
+                bytes,
+                "{}",
+                encoding.name()
+            );
+        }
+    }
+
+    #[test]
+    fn maps_the_windows_1252_specials() {
+        let bytes = [0x80, 0x93, 0x94, 0x99, 0x9f];
+        assert_eq!(
+            Encoding::Windows1252.decode(&bytes),
+            "\u{20ac}\u{201c}\u{201d}\u{2122}\u{178}"
+        );
+        assert_eq!(Encoding::Latin1.decode(&[0x80]), "\u{80}");
+        assert_eq!(Encoding::Windows1252.encode("\u{20ac}"), Ok(vec![0x80]));
+        // the latin-1 control chars windows-1252 replaces can't be written
+        assert_eq!(Encoding::Windows1252.encode("\u{80}"), Err('\u{80}'));
+    }
+
+    #[test]
+    fn fails_on_chars_the_encoding_lacks() {
+        assert_eq!(Encoding::Latin1.encode("a\u{20ac}"), Err('\u{20ac}'));
+        assert_eq!(Encoding::Windows1252.encode("\u{3b1}"), Err('\u{3b1}'));
+    }
+
+    #[test]
+    fn detects_encodings() {
+        assert_eq!(detect(b"\xef\xbb\xbfa"), (Encoding::Utf8, 3));
+        assert_eq!(detect(b"\xff\xfea\0"), (Encoding::Utf16Le, 2));
+        assert_eq!(detect(b"\xfe\xff\0a"), (Encoding::Utf16Be, 2));
+        assert_eq!(detect(b"a\0b\0c\0d\0"), (Encoding::Utf16Le, 0));
+        assert_eq!(detect(b"\0a\0b\0c\0d"), (Encoding::Utf16Be, 0));
+        assert_eq!(detect("caf\u{e9}".as_bytes()), (Encoding::Utf8, 0));
+        assert_eq!(detect(b"caf\xe9 \x80"), (Encoding::Windows1252, 0));
+        assert_eq!(detect(b"caf\xe9 \x81"), (Encoding::Latin1, 0));
+    }
+
+    #[test]
+    fn finds_encodings_by_name() {
+        assert_eq!(Encoding::from_name("UTF-8"), Some(Encoding::Utf8));
+        assert_eq!(Encoding::from_name("utf_16"), Some(Encoding::Utf16Le));
+        assert_eq!(Encoding::from_name("cp1252"), Some(Encoding::Windows1252));
+        assert_eq!(Encoding::from_name("ascii"), None);
+    }
+}
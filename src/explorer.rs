@@ -0,0 +1,420 @@
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::style::Stylize;
+use ignore::WalkBuilder;
+
+use crate::functions::{char_count, move_to};
+use crate::{on_main, Mode};
+
+// the sidebar never takes more than this or a third of the terminal
+const MAX_WIDTH: usize = 32;
+
+struct Entry {
+    path: PathBuf,
+    depth: usize,
+    is_dir: bool,
+}
+
+pub enum ExplorerAction {
+    None,
+    Open(PathBuf),
+    // focus goes back to the editor, the tree stays visible
+    Leave,
+    Hide,
+    Reveal,
+    // the directory the new file or directory goes into
+    NewFile(PathBuf),
+    NewDirectory(PathBuf),
+    Rename(PathBuf),
+    Delete(PathBuf),
+}
+
+// tree of the working directory shown next to the editor, CTRL+B toggles it
+pub struct Explorer {
+    pub visible: bool,
+    root: PathBuf,
+    expanded: HashSet<PathBuf>,
+    // the entries of all expanded directories, in the order they are shown
+    entries: Vec<Entry>,
+    selected: usize,
+    scroll: usize,
+    return_mode: Mode,
+}
+
+impl Explorer {
+    pub fn new() -> Self {
+        Self {
+            visible: false,
+            root: PathBuf::new(),
+            expanded: HashSet::new(),
+            entries: Vec::new(),
+            selected: 0,
+            scroll: 0,
+            return_mode: Mode::WriteMode,
+        }
+    }
+
+    // shows the tree if it's hidden and gives it the focus
+    pub fn focus(&mut self, root: PathBuf, return_mode: Mode) {
+        if !self.visible || self.root != root {
+            self.root = root;
+            self.visible = true;
+            self.refresh();
+        }
+        self.return_mode = return_mode;
+    }
+
+    pub fn get_return_mode(&self) -> Mode {
+        self.return_mode
+    }
+
+    // columns taken away from the editor
+    pub fn width(&self, term_width: usize) -> usize {
+        match self.visible {
+            true => MAX_WIDTH.min(term_width / 3),
+            false => 0,
+        }
+    }
+
+    // expands the directories above a file and selects it
+    pub fn reveal(&mut self, path: &Path, editor_height: &usize) {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return;
+        };
+
+        let mut directory = self.root.clone();
+        if let Some(parent) = relative.parent() {
+            for part in parent.components() {
+                directory.push(part);
+                self.expanded.insert(directory.clone());
+            }
+        }
+
+        self.refresh();
+        self.select(path);
+        self.keep_visible(editor_height);
+    }
+
+    // reads the directories again, the selection stays on the same path
+    pub fn refresh(&mut self) {
+        let selected = self.selected_path();
+
+        self.entries.clear();
+        self.expanded.retain(|path| path.is_dir());
+        let root = self.root.clone();
+        self.add_children(&root, 0);
+
+        self.selected = 0;
+        if let Some(path) = selected {
+            self.select(&path);
+        }
+    }
+
+    fn add_children(&mut self, directory: &Path, depth: usize) {
+        for (path, is_dir) in list_directory(directory) {
+            let expanded = is_dir && self.expanded.contains(&path);
+            self.entries.push(Entry {
+                path: path.clone(),
+                depth,
+                is_dir,
+            });
+            if expanded {
+                self.add_children(&path, depth + 1);
+            }
+        }
+    }
+
+    fn select(&mut self, path: &Path) {
+        if let Some(i) = self.entries.iter().position(|entry| entry.path == path) {
+            self.selected = i;
+        }
+    }
+
+    pub fn selected_path(&self) -> Option<PathBuf> {
+        self.entries
+            .get(self.selected)
+            .map(|entry| entry.path.clone())
+    }
+
+    // a click on one of the rows below the title, returns false outside of the list
+    pub fn click(&mut self, row: usize, editor_height: &usize) -> bool {
+        if row >= *editor_height || self.scroll + row >= self.entries.len() {
+            return false;
+        }
+        self.selected = self.scroll + row;
+        true
+    }
+
+    pub fn handle_key_event(
+        &mut self,
+        key_event: KeyEvent,
+        editor_height: &usize,
+    ) -> ExplorerAction {
+        let last = self.entries.len().saturating_sub(1);
+        let entry = self.entries.get(self.selected);
+        let mut action = ExplorerAction::None;
+
+        match key_event.code {
+            KeyCode::Esc => return ExplorerAction::Leave,
+            KeyCode::Char('b') if key_event.modifiers == KeyModifiers::CONTROL => {
+                return ExplorerAction::Hide
+            }
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected = (self.selected + 1).min(last),
+            KeyCode::PageUp => self.selected = self.selected.saturating_sub(*editor_height),
+            KeyCode::PageDown => self.selected = (self.selected + editor_height).min(last),
+            KeyCode::Home => self.selected = 0,
+            KeyCode::End => self.selected = last,
+            KeyCode::Enter | KeyCode::Right => match entry {
+                Some(entry) if entry.is_dir => {
+                    let path = entry.path.clone();
+                    let expanded = self.expanded.contains(&path);
+                    if expanded && key_event.code == KeyCode::Right {
+                        // already open, step into it
+                        self.selected = (self.selected + 1).min(last);
+                    } else if expanded {
+                        self.expanded.remove(&path);
+                        self.refresh();
+                    } else {
+                        self.expanded.insert(path);
+                        self.refresh();
+                    }
+                }
+                Some(entry) if key_event.code == KeyCode::Enter => {
+                    action = ExplorerAction::Open(entry.path.clone())
+                }
+                _ => {}
+            },
+            KeyCode::Left => match entry {
+                Some(entry) if entry.is_dir && self.expanded.contains(&entry.path) => {
+                    let path = entry.path.clone();
+                    self.expanded.remove(&path);
+                    self.refresh();
+                }
+                Some(entry) => {
+                    // up to the directory it's in
+                    if let Some(parent) = entry.path.parent().map(Path::to_path_buf) {
+                        self.select(&parent);
+                    }
+                }
+                None => {}
+            },
+            KeyCode::Char('a') => action = ExplorerAction::NewFile(self.target_directory()),
+            KeyCode::Char('A') => action = ExplorerAction::NewDirectory(self.target_directory()),
+            KeyCode::Char('r') => {
+                if let Some(entry) = entry {
+                    action = ExplorerAction::Rename(entry.path.clone());
+                }
+            }
+            KeyCode::Char('d') | KeyCode::Delete => {
+                if let Some(entry) = entry {
+                    action = ExplorerAction::Delete(entry.path.clone());
+                }
+            }
+            KeyCode::Char('f') => action = ExplorerAction::Reveal,
+            _ => {}
+        }
+
+        self.keep_visible(editor_height);
+        action
+    }
+
+    fn keep_visible(&mut self, editor_height: &usize) {
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + editor_height {
+            self.scroll = self.selected + 1 - editor_height;
+        }
+    }
+
+    // the selected directory, or the one the selected file is in
+    fn target_directory(&self) -> PathBuf {
+        match self.entries.get(self.selected) {
+            Some(entry) if entry.is_dir => entry.path.clone(),
+            Some(entry) => entry
+                .path
+                .parent()
+                .map_or(self.root.clone(), Path::to_path_buf),
+            None => self.root.clone(),
+        }
+    }
+
+    pub fn create(&mut self, directory: &Path, name: &str, is_dir: bool) -> io::Result<PathBuf> {
+        let path = directory.join(check_name(name)?);
+
+        if is_dir {
+            if path.exists() {
+                return Err(io::Error::from(ErrorKind::AlreadyExists));
+            }
+            fs::create_dir_all(&path)?;
+        } else {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)?;
+        }
+
+        self.reveal_created(&path);
+        Ok(path)
+    }
+
+    pub fn rename(&mut self, path: &Path, name: &str) -> io::Result<PathBuf> {
+        let new_path = path.with_file_name(check_name(name)?);
+        if new_path.exists() {
+            return Err(io::Error::from(ErrorKind::AlreadyExists));
+        }
+        fs::rename(path, &new_path)?;
+
+        // expanded directories inside keep being expanded
+        self.expanded = self
+            .expanded
+            .drain()
+            .map(|expanded| match expanded.strip_prefix(path) {
+                Ok(rest) => new_path.join(rest),
+                Err(_) => expanded,
+            })
+            .collect();
+
+        self.reveal_created(&new_path);
+        Ok(new_path)
+    }
+
+    pub fn delete(&mut self, path: &Path) -> io::Result<()> {
+        match path.is_dir() {
+            true => fs::remove_dir_all(path)?,
+            false => fs::remove_file(path)?,
+        }
+
+        self.refresh();
+        self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+        Ok(())
+    }
+
+    fn reveal_created(&mut self, path: &Path) {
+        let mut directory = path.parent();
+        while let Some(parent) = directory {
+            if !parent.starts_with(&self.root) || parent == self.root {
+                break;
+            }
+            self.expanded.insert(parent.to_path_buf());
+            directory = parent.parent();
+        }
+
+        self.refresh();
+        self.select(path);
+    }
+
+    // draws the tree at the right of the editor, below the menu bar
+    pub fn draw(
+        &self,
+        x: usize,
+        term_width: &usize,
+        height: &usize,
+        focused: bool,
+        open_file: &Path,
+    ) {
+        let width = self.width(*term_width);
+        let editor_height = height.saturating_sub(4);
+
+        let root_name = self
+            .root
+            .file_name()
+            .map_or(self.root.display().to_string(), |name| {
+                name.to_string_lossy().to_string()
+            });
+        let title = fit(&format!(" EXPLORER: {}", root_name), width - 1);
+
+        let _ = move_to(x as u16, 1);
+        print!("{}", on_main("│").dark_grey());
+        print!("{}", on_main(&title).bold());
+        let _ = move_to(x as u16, 2);
+        print!("{}", on_main("│").dark_grey());
+        print!("{}", on_main(&str::repeat(" ", width - 1)));
+
+        for row in 0..editor_height {
+            let _ = move_to(x as u16, (row + 3) as u16);
+            print!("{}", on_main("│").dark_grey());
+
+            let i = self.scroll + row;
+            let Some(entry) = self.entries.get(i) else {
+                print!("{}", on_main(&str::repeat(" ", width - 1)));
+                continue;
+            };
+
+            let name = entry
+                .path
+                .file_name()
+                .map_or(String::new(), |name| name.to_string_lossy().to_string());
+            let marker = match (entry.is_dir, self.expanded.contains(&entry.path)) {
+                (true, true) => "▾ ",
+                (true, false) => "▸ ",
+                (false, _) => "  ",
+            };
+            let text = str::repeat("  ", entry.depth) + marker + &name;
+            let text = fit(&format!(" {}", text), width - 1);
+
+            if i == self.selected && focused {
+                print!("{}", text.black().on_white());
+            } else if i == self.selected {
+                print!("{}", text.on_dark_grey());
+            } else if entry.path == open_file {
+                print!("{}", on_main(&text).yellow());
+            } else if entry.is_dir {
+                print!("{}", on_main(&text).cyan());
+            } else {
+                print!("{}", on_main(&text));
+            }
+        }
+    }
+}
+
+// the entries of one directory, directories first. hidden files and what
+// .gitignore covers are left out, the same way the file finder does it
+fn list_directory(directory: &Path) -> Vec<(PathBuf, bool)> {
+    let mut entries: Vec<(PathBuf, bool)> = WalkBuilder::new(directory)
+        .require_git(false)
+        .max_depth(Some(1))
+        .build()
+        .flatten()
+        .filter(|entry| entry.depth() == 1)
+        .map(|entry| {
+            let is_dir = entry.file_type().is_some_and(|kind| kind.is_dir());
+            (entry.into_path(), is_dir)
+        })
+        .collect();
+
+    entries.sort_by_key(|(path, is_dir)| {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase());
+        (!is_dir, name)
+    });
+    entries
+}
+
+// names can contain / to create something in a new subdirectory, but can't leave it
+fn check_name(name: &str) -> io::Result<&str> {
+    let name = name.trim();
+    let invalid = name.is_empty()
+        || name.starts_with('/')
+        || name.split('/').any(|part| part.is_empty() || part == "..");
+
+    match invalid {
+        true => Err(io::Error::new(ErrorKind::InvalidInput, "not a valid name")),
+        false => Ok(name),
+    }
+}
+
+// cuts the text or pads it with spaces to exactly width chars
+fn fit(text: &str, width: usize) -> String {
+    let text: String = text.chars().take(width).collect();
+    let padding = width - char_count(&text);
+    text + &str::repeat(" ", padding)
+}
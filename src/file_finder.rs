@@ -0,0 +1,365 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::style::Stylize;
+use ignore::WalkBuilder;
+
+use crate::encoding;
+use crate::functions::char_count;
+use crate::{on_main, on_secondary, Mode};
+
+// huge trees get cut off instead of freezing the editor
+const MAX_FILES: usize = 50_000;
+// the preview is left out when the list would get narrower than this
+const MIN_LIST_WIDTH: usize = 40;
+
+const CONSECUTIVE_BONUS: i64 = 6;
+const WORD_START_BONUS: i64 = 8;
+const FILE_NAME_BONUS: i64 = 4;
+const GAP_PENALTY: i64 = 1;
+
+// picks a file under the working directory by typing parts of its path
+pub struct FileFinder {
+    root: PathBuf,
+    // paths relative to the root, with / between the parts
+    files: Vec<String>,
+    query: String,
+    // index into files and the matched char positions, best match first
+    matches: Vec<(usize, Vec<usize>)>,
+    selected: usize,
+    scroll: usize,
+    return_mode: Mode,
+    // the selected file and the start of it, read again only once another
+    // file gets selected
+    preview: Option<(PathBuf, Option<String>)>,
+}
+
+impl FileFinder {
+    pub fn new() -> Self {
+        Self {
+            root: PathBuf::new(),
+            files: Vec::new(),
+            query: String::new(),
+            matches: Vec::new(),
+            selected: 0,
+            scroll: 0,
+            return_mode: Mode::WriteMode,
+            preview: None,
+        }
+    }
+
+    // lists the files again every time, skipping what .gitignore covers
+    pub fn open(&mut self, root: PathBuf, return_mode: Mode) {
+        self.files = WalkBuilder::new(&root)
+            .require_git(false)
+            .build()
+            .flatten()
+            .filter(|entry| entry.file_type().is_some_and(|kind| kind.is_file()))
+            .filter_map(|entry| {
+                let relative = entry.path().strip_prefix(&root).ok()?;
+                Some(relative.to_string_lossy().replace('\\', "/"))
+            })
+            .take(MAX_FILES)
+            .collect();
+        self.files.sort();
+
+        self.root = root;
+        self.query.clear();
+        self.return_mode = return_mode;
+        // the file may have changed since the finder was open
+        self.preview = None;
+        self.update();
+    }
+
+    pub fn get_return_mode(&self) -> Mode {
+        self.return_mode
+    }
+
+    pub fn selected_path(&self) -> Option<PathBuf> {
+        let (file, _) = self.matches.get(self.selected)?;
+        Some(self.root.join(&self.files[*file]))
+    }
+
+    // returns true once the finder is closed, ENTER opens the selected file
+    pub fn handle_key_event(&mut self, key_event: KeyEvent, editor_height: &usize) -> bool {
+        let last = self.matches.len().saturating_sub(1);
+
+        match key_event.code {
+            KeyCode::Esc | KeyCode::Enter => return true,
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected = (self.selected + 1).min(last),
+            KeyCode::PageUp => self.selected = self.selected.saturating_sub(*editor_height),
+            KeyCode::PageDown => self.selected = (self.selected + editor_height).min(last),
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.update();
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.update();
+            }
+            _ => {}
+        }
+
+        self.keep_visible(editor_height);
+        false
+    }
+
+    // keeps the selection on screen, also after the terminal got smaller
+    pub fn keep_visible(&mut self, editor_height: &usize) {
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + editor_height {
+            self.scroll = self.selected + 1 - editor_height;
+        }
+    }
+
+    fn update(&mut self) {
+        let query: Vec<char> = self
+            .query
+            .chars()
+            .map(|c| c.to_lowercase().next().unwrap_or(c))
+            .collect();
+
+        let mut scored: Vec<(i64, usize, Vec<usize>)> = self
+            .files
+            .iter()
+            .enumerate()
+            .filter_map(|(i, file)| {
+                let (score, positions) = fuzzy_match(&query, file)?;
+                Some((score, i, positions))
+            })
+            .collect();
+
+        // best score first, shorter paths win ties
+        scored.sort_by(|a, b| {
+            b.0.cmp(&a.0)
+                .then(self.files[a.1].len().cmp(&self.files[b.1].len()))
+                .then(a.1.cmp(&b.1))
+        });
+
+        self.matches = scored
+            .into_iter()
+            .map(|(_, i, positions)| (i, positions))
+            .collect();
+        self.selected = 0;
+        self.scroll = 0;
+    }
+
+    // the list on the left, the beginning of the selected file on the right
+    pub fn draw(&mut self, width: &usize, editor_height: &usize) {
+        let list_width = match width / 2 >= MIN_LIST_WIDTH {
+            true => width / 2,
+            false => *width,
+        };
+        let preview = match list_width < *width {
+            true => self.preview(*editor_height),
+            false => Vec::new(),
+        };
+
+        let title = format!(
+            " Open file: {} of {} files in {}",
+            self.matches.len(),
+            self.files.len(),
+            self.root.display()
+        );
+        let title: String = title.chars().take(*width).collect();
+
+        println!();
+        print!("{}", on_secondary(&title));
+        println!(
+            "{}",
+            on_secondary(&str::repeat(" ", width - char_count(&title)))
+        );
+        println!("{}", on_secondary(&str::repeat(" ", *width)));
+
+        for row in 0..*editor_height {
+            let i = self.scroll + row;
+            let mut written = 0;
+
+            if let Some((file, positions)) = self.matches.get(i) {
+                written = self.draw_entry(&self.files[*file], positions, i, list_width);
+            }
+            print!("{}", on_secondary(&str::repeat(" ", list_width - written)));
+
+            if list_width < *width {
+                let line = preview.get(row).map_or("", |line| line.as_str());
+                let line: String = line.chars().take(width - list_width - 3).collect();
+                print!("{}", on_secondary(" │ ").dark_grey());
+                print!("{}", on_secondary(&line).dark_grey());
+                print!(
+                    "{}",
+                    on_secondary(&str::repeat(
+                        " ",
+                        width - list_width - 3 - char_count(&line)
+                    ))
+                );
+            }
+            println!();
+        }
+    }
+
+    // one path with the matched characters marked, returns its width
+    fn draw_entry(&self, file: &str, positions: &[usize], i: usize, width: usize) -> usize {
+        let chars: Vec<char> = format!(" {}", file).chars().take(width).collect();
+
+        for (n, c) in chars.iter().enumerate() {
+            let matched = n > 0 && positions.contains(&(n - 1));
+            let text = c.to_string();
+
+            match (i == self.selected, matched) {
+                (true, true) => print!("{}", text.black().on_white().bold()),
+                (true, false) => print!("{}", text.black().on_white()),
+                (false, true) => print!("{}", on_secondary(&text).yellow().bold()),
+                (false, false) => print!("{}", on_secondary(&text)),
+            }
+        }
+
+        chars.len()
+    }
+
+    fn preview(&mut self, height: usize) -> Vec<String> {
+        let Some(path) = self.selected_path() else {
+            return Vec::new();
+        };
+
+        if self.preview.as_ref().is_none_or(|(read, _)| *read != path) {
+            let contents = read_start(&path);
+            self.preview = Some((path, contents));
+        }
+
+        match &self.preview {
+            Some((_, Some(contents))) => contents
+                .lines()
+                .take(height)
+                .map(|line| line.replace('\t', "    "))
+                .collect(),
+            _ => vec![String::from("(binary or unreadable file)")],
+        }
+    }
+
+    pub fn draw_input(&self, width: &usize) {
+        let prefix = "OPEN /> ";
+
+        print!("{}", prefix.red());
+        print!("{}", on_main(&self.query));
+        print!("{}", ' '.on_red());
+
+        let written = prefix.len() + char_count(&self.query) + 1;
+        print!(
+            "{}",
+            on_main(&str::repeat(" ", width.saturating_sub(written)))
+        );
+    }
+}
+
+// the first few kilobytes of a text file
+fn read_start(path: &Path) -> Option<String> {
+    let mut bytes = Vec::new();
+    File::open(path)
+        .ok()?
+        .take(16 * 1024)
+        .read_to_end(&mut bytes)
+        .ok()?;
+    if encoding::is_binary(&bytes) {
+        return None;
+    }
+
+    let (file_encoding, bom_len) = encoding::detect(&bytes);
+    Some(file_encoding.decode(&bytes[bom_len..]))
+}
+
+// scores how well the query matches the path as a subsequence, matches at
+// the start of words and in the file name count more, gaps count less.
+// returns the best score and the matched char positions
+pub fn fuzzy_match(query: &[char], path: &str) -> Option<(i64, Vec<usize>)> {
+    if query.is_empty() {
+        return Some((0, Vec::new()));
+    }
+
+    let chars: Vec<char> = path.chars().collect();
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+
+    let name_start = path.rfind('/').map_or(0, |i| char_count(&path[..i]) + 1);
+    let bonus = |j: usize| {
+        let mut bonus = 0;
+        let word_start = match j.checked_sub(1).map(|k| chars[k]) {
+            None => true,
+            Some(previous) => {
+                matches!(previous, '/' | '_' | '-' | '.' | ' ')
+                    || (previous.is_lowercase() && chars[j].is_uppercase())
+            }
+        };
+        if word_start {
+            bonus += WORD_START_BONUS;
+        }
+        if j >= name_start {
+            bonus += FILE_NAME_BONUS;
+        }
+        bonus
+    };
+
+    // best score with query[i] at chars[j], and where query[i - 1] was
+    let n = chars.len();
+    let mut scores = vec![vec![None; n]; query.len()];
+    let mut parents = vec![vec![0; n]; query.len()];
+
+    for (i, query_char) in query.iter().enumerate() {
+        // best earlier position that leaves a gap, already reduced by the gap
+        let mut carry: Option<(i64, usize)> = None;
+
+        for j in 0..n {
+            if i > 0 && j >= 2 {
+                if let Some(score) = scores[i - 1][j - 2] {
+                    if carry.is_none_or(|(best, _)| score > best) {
+                        carry = Some((score, j - 2));
+                    }
+                }
+                carry = carry.map(|(best, k)| (best - GAP_PENALTY, k));
+            }
+
+            if lower[j] != *query_char {
+                continue;
+            }
+
+            let previous = match i {
+                0 => Some((0, 0)),
+                _ => {
+                    let adjacent = j
+                        .checked_sub(1)
+                        .and_then(|k| scores[i - 1][k].map(|score| (score + CONSECUTIVE_BONUS, k)));
+                    match (adjacent, carry) {
+                        (Some(a), Some(c)) => Some(if a.0 >= c.0 { a } else { c }),
+                        (a, c) => a.or(c),
+                    }
+                }
+            };
+
+            if let Some((score, parent)) = previous {
+                scores[i][j] = Some(score + bonus(j));
+                parents[i][j] = parent;
+            }
+        }
+    }
+
+    let last = query.len() - 1;
+    let (mut j, score) = scores[last]
+        .iter()
+        .enumerate()
+        .filter_map(|(j, score)| score.map(|score| (j, score)))
+        .max_by_key(|(_, score)| *score)?;
+
+    let mut positions = vec![j];
+    for i in (1..=last).rev() {
+        j = parents[i][j];
+        positions.push(j);
+    }
+    positions.reverse();
+
+    Some((score, positions))
+}
@@ -1,16 +1,23 @@
 use core::num;
 use std::{
     borrow::Borrow,
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
     io::{self, Write},
+    path::Path,
+    time::SystemTime,
 };
 
 use crossterm::{
     cursor::MoveTo,
     execute,
-    style::{Color, StyledContent, Stylize},
+    style::{StyledContent, Stylize},
     terminal::{Clear, ClearType},
 };
 
+use crate::theme::{self, rgb};
+
 pub fn move_to(x: u16, y: u16) -> io::Result<()> {
     execute!(io::stdout(), MoveTo(x, y))
 }
@@ -45,6 +52,30 @@ pub fn for_real_clear() -> io::Result<()> {
     Ok(())
 }
 
+// short, user facing description of an io error for the status bar
+pub fn describe_error(error: &io::Error) -> String {
+    match error.kind() {
+        io::ErrorKind::NotFound => String::from("file or directory not found"),
+        io::ErrorKind::PermissionDenied => String::from("permission denied"),
+        io::ErrorKind::AlreadyExists => String::from("file already exists"),
+        _ => error.to_string(),
+    }
+}
+
+// quick fingerprint of file contents, to tell whether they changed
+pub fn hash_of(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+// none when the file is gone or the platform doesn't keep the time
+pub fn modified_time(file_path: &Path) -> Option<SystemTime> {
+    fs::metadata(file_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
 pub fn clamp(num: usize, min: usize, max: usize) -> usize {
     if num > max {
         max
@@ -56,27 +87,15 @@ pub fn clamp(num: usize, min: usize, max: usize) -> usize {
 }
 
 pub fn on_main(text: &str) -> StyledContent<&str> {
-    return text.on(Color::Rgb {
-        r: 35,
-        g: 35,
-        b: 45,
-    });
+    return text.on(rgb(theme::current().main));
 }
 
 pub fn on_secondary(text: &str) -> StyledContent<&str> {
-    return text.on(Color::Rgb {
-        r: 47,
-        g: 47,
-        b: 56,
-    });
+    return text.on(rgb(theme::current().secondary));
 }
 
 pub fn styled_on_secondary(text: StyledContent<String>) -> StyledContent<String> {
-    return text.on(Color::Rgb {
-        r: 47,
-        g: 47,
-        b: 56,
-    });
+    return text.on(rgb(theme::current().secondary));
 }
 
 pub fn jump_to_editor_point(
@@ -93,6 +112,24 @@ pub fn jump_to_editor_point(
     }
 }
 
+// cursor positions count characters, not bytes
+pub fn char_count(line: &str) -> usize {
+    line.chars().count()
+}
+
+// byte offset of a cursor position, for slicing and inserting into a line
+pub fn byte_index(line: &str, char_index: usize) -> usize {
+    line.char_indices()
+        .nth(char_index)
+        .map_or(line.len(), |(index, _)| index)
+}
+
+// keeps the cursor inside the buffer after its lines got replaced
+pub fn fit_cursor(current_line: &mut usize, current_char: &mut usize, lines: &[String]) {
+    *current_line = (*current_line).min(lines.len() - 1);
+    *current_char = (*current_char).min(char_count(&lines[*current_line]));
+}
+
 pub fn move_down(
     current_line: &mut usize,
     current_char: &mut usize,
@@ -106,8 +143,8 @@ pub fn move_down(
 
     *current_line += 1;
 
-    if *current_char >= lines[*current_line].len() {
-        *current_char = lines[*current_line].len()
+    if *current_char >= char_count(&lines[*current_line]) {
+        *current_char = char_count(&lines[*current_line])
     }
 
     jump_to_editor_point(current_line, current_scroll, editor_height);
@@ -133,8 +170,8 @@ pub fn move_up(
         *current_scroll = *current_line;
     }
 
-    if *current_char >= lines[*current_line].len() {
-        *current_char = lines[*current_line].len()
+    if *current_char >= char_count(&lines[*current_line]) {
+        *current_char = char_count(&lines[*current_line])
     }
     jump_to_editor_point(current_line, current_scroll, editor_height);
     clear()?;
@@ -147,19 +184,19 @@ pub fn move_right(
     lines: &Vec<String>,
     whole_word: bool,
 ) -> io::Result<bool> {
-    if *current_char >= lines[*current_line].len() {
+    if *current_char >= char_count(&lines[*current_line]) {
         return Ok(false);
     }
 
     *current_char += 1;
 
     if whole_word {
-        while *current_char < lines[*current_line].len()
+        while *current_char < char_count(&lines[*current_line])
             && lines[*current_line].chars().nth(*current_char).unwrap() == ' '
         {
             *current_char += 1;
         }
-        while *current_char < lines[*current_line].len()
+        while *current_char < char_count(&lines[*current_line])
             && lines[*current_line].chars().nth(*current_char).unwrap() != ' '
         {
             *current_char += 1;
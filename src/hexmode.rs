@@ -0,0 +1,233 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::style::{StyledContent, Stylize};
+
+use crate::{on_secondary, styled_on_secondary, ChangedLineType};
+
+const ROW_LEN: usize = 16;
+
+pub struct HexEditor {
+    pub bytes: Vec<u8>,
+    // bytes as they are on disk
+    saved_bytes: Vec<u8>,
+    // byte under the cursor, one past the end appends a new byte
+    cursor: usize,
+    low_nibble: bool,
+    ascii_column: bool,
+    scroll: usize,
+}
+
+impl HexEditor {
+    pub fn new(bytes: Vec<u8>, saved_bytes: Vec<u8>) -> Self {
+        Self {
+            bytes,
+            saved_bytes,
+            cursor: 0,
+            low_nibble: false,
+            ascii_column: false,
+            scroll: 0,
+        }
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.bytes != self.saved_bytes
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.saved_bytes = self.bytes.clone();
+    }
+
+    pub fn set_saved_bytes(&mut self, saved_bytes: Vec<u8>) {
+        self.saved_bytes = saved_bytes;
+    }
+
+    pub fn get_cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn handle_key_event(
+        &mut self,
+        key_event: KeyEvent,
+        editor_height: &usize,
+    ) -> ChangedLineType {
+        let page = editor_height * ROW_LEN;
+
+        match key_event.code {
+            KeyCode::Left => self.move_left(),
+            KeyCode::Right => self.move_right(),
+            KeyCode::Backspace => self.move_left(),
+            KeyCode::Up => self.move_to(self.cursor.saturating_sub(ROW_LEN)),
+            KeyCode::Down => self.move_to(self.cursor + ROW_LEN),
+            KeyCode::PageUp => self.move_to(self.cursor.saturating_sub(page)),
+            KeyCode::PageDown => self.move_to(self.cursor + page),
+            KeyCode::Home => self.move_to(self.cursor - self.cursor % ROW_LEN),
+            KeyCode::End => self.move_to(self.cursor - self.cursor % ROW_LEN + ROW_LEN - 1),
+            KeyCode::Tab => {
+                self.ascii_column = !self.ascii_column;
+                self.low_nibble = false;
+            }
+            KeyCode::Char(c) => {
+                if self.ascii_column {
+                    if c.is_ascii() && !c.is_ascii_control() {
+                        self.write_byte(c as u8);
+                        self.move_right();
+                    }
+                } else if let Some(digit) = c.to_digit(16) {
+                    self.write_nibble(digit as u8);
+                    self.move_right();
+                }
+            }
+            _ => return ChangedLineType::None,
+        }
+
+        self.scroll_to_cursor(editor_height);
+        ChangedLineType::All
+    }
+
+    pub fn scroll(&mut self, rows: isize, editor_height: &usize) {
+        let last_row = self.bytes.len() / ROW_LEN;
+        self.scroll = (self.scroll as isize + rows).clamp(0, last_row as isize) as usize;
+
+        // keep the cursor on screen like the text editor does
+        let cursor_row = self.cursor / ROW_LEN;
+        if cursor_row < self.scroll {
+            self.move_to(self.scroll * ROW_LEN + self.cursor % ROW_LEN);
+        } else if cursor_row >= self.scroll + editor_height {
+            self.move_to((self.scroll + editor_height - 1) * ROW_LEN + self.cursor % ROW_LEN);
+        }
+    }
+
+    fn move_to(&mut self, cursor: usize) {
+        self.cursor = cursor.min(self.bytes.len());
+        self.low_nibble = false;
+    }
+
+    fn move_left(&mut self) {
+        if self.low_nibble {
+            self.low_nibble = false;
+        } else if self.cursor > 0 {
+            self.cursor -= 1;
+            self.low_nibble = !self.ascii_column;
+        }
+    }
+
+    fn move_right(&mut self) {
+        if self.cursor >= self.bytes.len() {
+            return;
+        }
+
+        if self.ascii_column || self.low_nibble {
+            self.cursor += 1;
+            self.low_nibble = false;
+        } else {
+            self.low_nibble = true;
+        }
+    }
+
+    fn write_byte(&mut self, byte: u8) {
+        if self.cursor == self.bytes.len() {
+            self.bytes.push(byte);
+        } else {
+            self.bytes[self.cursor] = byte;
+        }
+    }
+
+    fn write_nibble(&mut self, nibble: u8) {
+        let old = self.bytes.get(self.cursor).copied().unwrap_or(0);
+
+        let byte = match self.low_nibble {
+            true => (old & 0xf0) | nibble,
+            false => (nibble << 4) | (old & 0x0f),
+        };
+
+        self.write_byte(byte);
+    }
+
+    fn scroll_to_cursor(&mut self, editor_height: &usize) {
+        let cursor_row = self.cursor / ROW_LEN;
+
+        if cursor_row < self.scroll {
+            self.scroll = cursor_row;
+        } else if cursor_row >= self.scroll + editor_height {
+            self.scroll = cursor_row + 1 - editor_height;
+        }
+    }
+
+    // offset, hex pairs and printable characters, 16 bytes per row
+    pub fn draw(&self, width: &usize, editor_height: &usize) {
+        for row in self.scroll..self.scroll + editor_height {
+            let start = row * ROW_LEN;
+            let mut written = 0;
+
+            if start <= self.bytes.len() {
+                let offset = format!("{:08x}", start);
+                if self.cursor / ROW_LEN == row {
+                    print!("{}", on_secondary(&offset));
+                } else {
+                    print!("{}", on_secondary(&offset).dark_grey());
+                }
+                print!("{}", on_secondary(" │ ").dark_grey());
+                written += offset.len() + 3;
+
+                for i in start..start + ROW_LEN {
+                    if i == start + ROW_LEN / 2 {
+                        print!("{}", on_secondary(" "));
+                        written += 1;
+                    }
+                    for (nibble, digit) in self.hex_digits(i).chars().enumerate() {
+                        let cursor_here = i == self.cursor && !self.ascii_column;
+                        let active = cursor_here && self.low_nibble == (nibble == 1);
+                        print!("{}", self.style_cell(digit, active, i == self.cursor));
+                    }
+                    print!("{}", on_secondary(" "));
+                    written += 3;
+                }
+
+                print!("{}", on_secondary("│ ").dark_grey());
+                written += 2;
+
+                for i in start..start + ROW_LEN {
+                    let character = match self.bytes.get(i) {
+                        Some(byte @ 0x20..=0x7e) => *byte as char,
+                        Some(_) => '.',
+                        None => ' ',
+                    };
+                    let active = i == self.cursor && self.ascii_column;
+                    print!("{}", self.style_cell(character, active, i == self.cursor));
+                    written += 1;
+                }
+            }
+
+            print!(
+                "{}",
+                on_secondary(&str::repeat(" ", width.saturating_sub(written)))
+            );
+            println!();
+        }
+    }
+
+    fn hex_digits(&self, i: usize) -> String {
+        match self.bytes.get(i) {
+            Some(byte) => format!("{:02x}", byte),
+            None if i == self.bytes.len() => String::from("__"),
+            None => String::from("  "),
+        }
+    }
+
+    // the active cell looks like the text cursor, its twin in the other column is dimmed
+    fn style_cell(
+        &self,
+        character: char,
+        active: bool,
+        cursor_byte: bool,
+    ) -> StyledContent<String> {
+        let text = character.to_string();
+
+        if active {
+            text.black().on_white()
+        } else if cursor_byte {
+            text.on_dark_grey()
+        } else {
+            styled_on_secondary(text.stylize())
+        }
+    }
+}
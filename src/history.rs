@@ -0,0 +1,124 @@
+// how many edits can be undone
+const MAX_ENTRIES: usize = 200;
+
+struct Snapshot {
+    lines: Vec<String>,
+    line: usize,
+    char: usize,
+}
+
+// undo and redo, every entry is a copy of the lines from before an edit
+pub struct History {
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    // where the next typed character has to go to continue the current entry
+    typing_at: Option<(usize, usize)>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            typing_at: None,
+        }
+    }
+
+    // remembers the lines as they were before an edit, a run of typed
+    // characters is undone as one
+    pub fn record(&mut self, lines: Vec<String>, line: usize, char: usize, typing: bool) {
+        let continues_typing = typing && self.typing_at == Some((line, char));
+        self.typing_at = typing.then_some((line, char + 1));
+        self.redo.clear();
+
+        if continues_typing {
+            return;
+        }
+
+        self.undo.push(Snapshot { lines, line, char });
+        if self.undo.len() > MAX_ENTRIES {
+            self.undo.remove(0);
+        }
+    }
+
+    // whether a char typed at a position belongs to the run being typed,
+    // which is undone with the lines from before the run
+    pub fn continues_typing(&self, position: (usize, usize)) -> bool {
+        self.typing_at == Some(position)
+    }
+
+    // a char typed on in the current run
+    pub fn typed(&mut self, (line, char): (usize, usize)) {
+        self.typing_at = Some((line, char + 1));
+        self.redo.clear();
+    }
+
+    // ends the current run once the cursor is anywhere but where it left off,
+    // so typing after moving away and back is undone on its own
+    pub fn cursor_at(&mut self, position: (usize, usize)) {
+        if self.typing_at != Some(position) {
+            self.typing_at = None;
+        }
+    }
+
+    // returns false when there is nothing to undo
+    pub fn undo(&mut self, lines: &mut Vec<String>, line: &mut usize, char: &mut usize) -> bool {
+        self.typing_at = None;
+        restore(&mut self.undo, &mut self.redo, lines, line, char)
+    }
+
+    pub fn redo(&mut self, lines: &mut Vec<String>, line: &mut usize, char: &mut usize) -> bool {
+        self.typing_at = None;
+        restore(&mut self.redo, &mut self.undo, lines, line, char)
+    }
+}
+
+// swaps the current lines with the newest snapshot of `from`
+fn restore(
+    from: &mut Vec<Snapshot>,
+    to: &mut Vec<Snapshot>,
+    lines: &mut Vec<String>,
+    line: &mut usize,
+    char: &mut usize,
+) -> bool {
+    let Some(snapshot) = from.pop() else {
+        return false;
+    };
+
+    to.push(Snapshot {
+        lines: std::mem::replace(lines, snapshot.lines),
+        line: *line,
+        char: *char,
+    });
+    *line = snapshot.line;
+    *char = snapshot.char;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        vec![String::from(text)]
+    }
+
+    #[test]
+    fn moving_the_cursor_ends_the_typing_run() {
+        let mut history = History::new();
+        history.record(lines(""), 0, 0, true);
+        history.cursor_at((0, 1));
+        assert!(history.continues_typing((0, 1)));
+        history.typed((0, 1));
+
+        // away and back again
+        history.cursor_at((0, 0));
+        history.cursor_at((0, 2));
+        assert!(!history.continues_typing((0, 2)));
+        history.record(lines("ab"), 0, 2, true);
+
+        let (mut text, mut line, mut char) = (lines("abc"), 0, 3);
+        assert!(history.undo(&mut text, &mut line, &mut char));
+        assert_eq!((text, line, char), (lines("ab"), 0, 2));
+    }
+}
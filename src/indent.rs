@@ -0,0 +1,201 @@
+use std::ops::Range;
+
+// how a buffer is indented, tabs are shown `width` columns wide
+#[derive(Clone, Copy, PartialEq)]
+pub struct Indent {
+    pub tabs: bool,
+    pub width: usize,
+}
+
+impl Indent {
+    pub fn spaces(width: usize) -> Self {
+        Self { tabs: false, width }
+    }
+
+    pub fn tabs(width: usize) -> Self {
+        Self { tabs: true, width }
+    }
+
+    // what one level of indentation is made of
+    pub fn unit(&self) -> String {
+        match self.tabs {
+            true => String::from("\t"),
+            false => str::repeat(" ", self.width),
+        }
+    }
+
+    // adds a level in front of the line, empty lines stay empty. returns
+    // how many chars were added
+    pub fn indent_line(&self, line: &mut String) -> usize {
+        if line.is_empty() {
+            return 0;
+        }
+        let unit = self.unit();
+        line.insert_str(0, &unit);
+        unit.len()
+    }
+
+    // removes a level from the front of the line, returns how many chars
+    // were removed
+    pub fn outdent_line(&self, line: &mut String) -> usize {
+        let removed = self.outdent_len(line);
+        line.drain(..removed);
+        removed
+    }
+
+    // how many chars at the start of the line one level less indentation
+    // removes, spaces go back to the previous indent stop
+    pub fn outdent_len(&self, line: &str) -> usize {
+        if line.starts_with('\t') {
+            return 1;
+        }
+
+        let spaces = line.len() - line.trim_start_matches(' ').len();
+        match spaces % self.width {
+            0 => spaces.min(self.width),
+            rest => rest,
+        }
+    }
+
+    // the bytes of the text in front of the cursor that backspace removes,
+    // a level of spaces at once while only spaces come before it and one
+    // char otherwise
+    pub fn backspace_range(&self, before: &str) -> Range<usize> {
+        let end = before.len();
+        if before.is_empty() || !before.bytes().all(|b| b == b' ') {
+            let last = before.chars().last().map_or(0, char::len_utf8);
+            return end - last..end;
+        }
+
+        let removed = match end % self.width {
+            0 => self.width,
+            rest => rest,
+        };
+        end - removed.min(end)..end
+    }
+
+    // for the status bar
+    pub fn describe(&self) -> String {
+        match self.tabs {
+            true => format!("Tabs: {}", self.width),
+            false => format!("Spaces: {}", self.width),
+        }
+    }
+
+    // whether any line with text is indented, there's nothing to detect otherwise
+    pub fn any_indented(lines: &[String]) -> bool {
+        lines
+            .iter()
+            .any(|line| !line.trim().is_empty() && line.starts_with([' ', '\t']))
+    }
+
+    // guesses from the lines' leading whitespace, files without indented
+    // lines get the fallback
+    pub fn detect(lines: &[String], fallback: Indent) -> Indent {
+        let mut tab_lines = 0;
+        let mut space_lines = 0;
+        // how often the indentation grew by 1 to 8 spaces between lines
+        let mut steps = [0; 9];
+        let mut previous = 0;
+
+        for line in lines {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            if line.starts_with('\t') {
+                tab_lines += 1;
+                continue;
+            }
+
+            let spaces = line.len() - line.trim_start_matches(' ').len();
+            if spaces > 0 {
+                space_lines += 1;
+            }
+            if spaces > previous && spaces - previous < steps.len() {
+                steps[spaces - previous] += 1;
+            }
+            previous = spaces;
+        }
+
+        if !Indent::any_indented(lines) {
+            return fallback;
+        }
+        if tab_lines > space_lines {
+            return Indent::tabs(fallback.width);
+        }
+
+        // the most common step wins, smaller ones on a tie
+        let width = (2..steps.len())
+            .rev()
+            .max_by_key(|step| steps[*step])
+            .filter(|step| steps[*step] > 0)
+            .unwrap_or(fallback.width);
+        Indent::spaces(width)
+    }
+}
+
+// the characters of a line as they are drawn, with the index of the char
+// each one comes from, tabs become spaces up to the next tab stop
+pub fn expand_tabs(line: &str, tab_width: usize) -> Vec<(char, usize)> {
+    let mut cells = Vec::new();
+
+    for (i, c) in line.chars().enumerate() {
+        match c {
+            '\t' => {
+                let stop = (cells.len() / tab_width + 1) * tab_width;
+                while cells.len() < stop {
+                    cells.push((' ', i));
+                }
+            }
+            c => cells.push((c, i)),
+        }
+    }
+
+    cells
+}
+
+// the char drawn at a column, the end of the line when past it
+pub fn char_at_column(line: &str, column: usize, tab_width: usize) -> usize {
+    match expand_tabs(line, tab_width).get(column) {
+        Some((_, i)) => *i,
+        None => line.chars().count(),
+    }
+}
+
+// the tabs and spaces a line starts with
+pub fn leading_whitespace(line: &str) -> &str {
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removes_a_level_of_spaces() {
+        let indent = Indent::spaces(4);
+        assert_eq!(indent.backspace_range("        "), 4..8);
+        assert_eq!(indent.backspace_range("      "), 4..6);
+        assert_eq!(indent.backspace_range("  "), 0..2);
+    }
+
+    #[test]
+    fn removes_one_char_after_text() {
+        let indent = Indent::spaces(4);
+        assert_eq!(indent.backspace_range("    a    "), 8..9);
+        assert_eq!(indent.backspace_range("\t  "), 2..3);
+        assert_eq!(indent.backspace_range("caf\u{e9}"), 3..5);
+        assert_eq!(indent.backspace_range("1 \u{20ac}"), 2..5);
+        assert_eq!(indent.backspace_range("\u{fc}"), 0..2);
+    }
+
+    #[test]
+    fn outdents_to_the_previous_stop() {
+        let indent = Indent::spaces(4);
+        assert_eq!(indent.outdent_len("      a"), 2);
+        assert_eq!(indent.outdent_len("        a"), 4);
+        assert_eq!(indent.outdent_len("\t\ta"), 1);
+        assert_eq!(indent.outdent_len("a"), 0);
+    }
+}
@@ -0,0 +1,615 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::actions::{self, Action};
+use crate::Mode;
+
+// the modes a binding works in
+#[derive(Clone, Copy, PartialEq)]
+enum Scope {
+    // whenever the editor itself has the focus
+    Editor,
+    // write and edit mode
+    Text,
+    Write,
+    Edit,
+    Hex,
+}
+
+const SCOPES: &[(Scope, &str)] = &[
+    (Scope::Editor, "editor"),
+    (Scope::Text, "text"),
+    (Scope::Write, "write"),
+    (Scope::Edit, "edit"),
+    (Scope::Hex, "hex"),
+];
+
+const NONE: KeyModifiers = KeyModifiers::NONE;
+const CONTROL: KeyModifiers = KeyModifiers::CONTROL;
+const ALT: KeyModifiers = KeyModifiers::ALT;
+const SHIFT: KeyModifiers = KeyModifiers::SHIFT;
+
+// a key with its modifiers, shifted letters are upper case without SHIFT
+type Chord = (KeyCode, KeyModifiers);
+
+// written the way the config file does, keys of a sequence are separated
+// by spaces
+type Preset = &'static [(Scope, &'static str, Action)];
+
+const DEFAULT: Preset = &[
+    (Scope::Editor, "esc", Action::Quit),
+    (Scope::Editor, "f2", Action::ToggleHex),
+    (Scope::Editor, "f1", Action::CommandPalette),
+    (Scope::Editor, "ctrl+shift+p", Action::CommandPalette),
+    (Scope::Editor, "ctrl+s", Action::Save),
+    (Scope::Editor, "ctrl+n", Action::NewFile),
+    (Scope::Editor, "ctrl+o", Action::OpenFile),
+    (Scope::Editor, "ctrl+b", Action::ToggleExplorer),
+    (Scope::Editor, "f10", Action::OpenMenu(0)),
+    (Scope::Editor, "alt+f", Action::OpenMenu(0)),
+    (Scope::Editor, "alt+e", Action::OpenMenu(1)),
+    (Scope::Editor, "alt+c", Action::OpenMenu(2)),
+    (Scope::Editor, "alt+s", Action::OpenMenu(3)),
+    (Scope::Text, "ctrl+f", Action::Find),
+    (Scope::Text, "ctrl+z", Action::Undo),
+    (Scope::Text, "ctrl+y", Action::Redo),
+    (Scope::Write, "alt+j", Action::EditMode),
+    (Scope::Edit, ":", Action::OpenConsole),
+    (Scope::Edit, "/", Action::Find),
+    (Scope::Edit, "n", Action::FindNext),
+    (Scope::Edit, "shift+n", Action::FindPrevious),
+    (Scope::Edit, "q", Action::WriteMode),
+    (Scope::Edit, "i", Action::CursorUp),
+    (Scope::Edit, "k", Action::CursorDown),
+    (Scope::Edit, "j", Action::CursorLeft),
+    (Scope::Edit, "l", Action::CursorRight),
+    (Scope::Edit, "alt+l", Action::NextWord),
+    (Scope::Edit, "alt+j", Action::PreviousWord),
+    (Scope::Edit, "u", Action::LineStart),
+    (Scope::Edit, "o", Action::LineEnd),
+    (Scope::Edit, "alt+i", Action::MoveLineUp),
+    (Scope::Edit, "alt+k", Action::MoveLineDown),
+    (Scope::Edit, "d", Action::DeleteLine),
+    (Scope::Edit, ">", Action::IndentLine),
+    (Scope::Edit, "<", Action::OutdentLine),
+    (Scope::Edit, "m", Action::MatchBracket),
+    (Scope::Edit, "c", Action::ToggleComment),
+    (Scope::Text, "alt+/", Action::ToggleComment),
+];
+
+// edit mode works like vim's normal mode, ESC leaves write mode
+const VIM: Preset = &[
+    (Scope::Editor, "f1", Action::CommandPalette),
+    (Scope::Editor, "ctrl+shift+p", Action::CommandPalette),
+    (Scope::Editor, "f2", Action::ToggleHex),
+    (Scope::Editor, "f10", Action::OpenMenu(0)),
+    (Scope::Editor, "ctrl+b", Action::ToggleExplorer),
+    (Scope::Write, "esc", Action::EditMode),
+    (Scope::Hex, "esc", Action::Quit),
+    (Scope::Edit, "i", Action::WriteMode),
+    (Scope::Edit, "h", Action::CursorLeft),
+    (Scope::Edit, "j", Action::CursorDown),
+    (Scope::Edit, "k", Action::CursorUp),
+    (Scope::Edit, "l", Action::CursorRight),
+    (Scope::Edit, "w", Action::NextWord),
+    (Scope::Edit, "b", Action::PreviousWord),
+    (Scope::Edit, "0", Action::LineStart),
+    (Scope::Edit, "$", Action::LineEnd),
+    (Scope::Edit, "d d", Action::DeleteLine),
+    (Scope::Edit, "> >", Action::IndentLine),
+    (Scope::Edit, "< <", Action::OutdentLine),
+    (Scope::Edit, "%", Action::MatchBracket),
+    (Scope::Edit, "g c c", Action::ToggleComment),
+    (Scope::Edit, "alt+k", Action::MoveLineUp),
+    (Scope::Edit, "alt+j", Action::MoveLineDown),
+    (Scope::Edit, "u", Action::Undo),
+    (Scope::Edit, "ctrl+r", Action::Redo),
+    (Scope::Edit, ":", Action::OpenConsole),
+    (Scope::Edit, "/", Action::Find),
+    (Scope::Edit, "n", Action::FindNext),
+    (Scope::Edit, "shift+n", Action::FindPrevious),
+    (Scope::Edit, "ctrl+p", Action::OpenFile),
+    (Scope::Edit, "g g", Action::GotoLine),
+];
+
+// everything in write mode with nano's CTRL and ALT keys
+const NANO: Preset = &[
+    (Scope::Editor, "ctrl+x", Action::Quit),
+    (Scope::Editor, "ctrl+o", Action::Save),
+    (Scope::Editor, "ctrl+r", Action::OpenFile),
+    (Scope::Editor, "ctrl+g", Action::CommandPalette),
+    (Scope::Editor, "f1", Action::CommandPalette),
+    (Scope::Editor, "f2", Action::ToggleHex),
+    (Scope::Editor, "f10", Action::OpenMenu(0)),
+    (Scope::Editor, "alt+b", Action::ToggleExplorer),
+    (Scope::Text, "ctrl+w", Action::Find),
+    (Scope::Text, "alt+w", Action::FindNext),
+    (Scope::Text, "alt+q", Action::FindPrevious),
+    (Scope::Text, "alt+r", Action::Replace),
+    (Scope::Text, "alt+g", Action::GotoLine),
+    (Scope::Text, "alt+u", Action::Undo),
+    (Scope::Text, "alt+e", Action::Redo),
+    (Scope::Text, "ctrl+k", Action::DeleteLine),
+    (Scope::Text, "alt+}", Action::IndentLine),
+    (Scope::Text, "alt+{", Action::OutdentLine),
+    (Scope::Text, "alt+]", Action::MatchBracket),
+    (Scope::Text, "alt+3", Action::ToggleComment),
+    (Scope::Text, "ctrl+a", Action::LineStart),
+    (Scope::Text, "ctrl+e", Action::LineEnd),
+    (Scope::Text, "ctrl+p", Action::CursorUp),
+    (Scope::Text, "ctrl+n", Action::CursorDown),
+    (Scope::Text, "ctrl+b", Action::CursorLeft),
+    (Scope::Text, "ctrl+f", Action::CursorRight),
+    (Scope::Text, "ctrl+space", Action::NextWord),
+    (Scope::Text, "alt+space", Action::PreviousWord),
+];
+
+const PRESETS: &[(&str, Preset)] = &[("default", DEFAULT), ("vim", VIM), ("nano", NANO)];
+
+struct Binding {
+    scope: Scope,
+    keys: Vec<Chord>,
+    action: Action,
+}
+
+// what a key press did
+#[derive(Clone, Copy)]
+pub enum KeyPress {
+    None,
+    // the start of a sequence, the next keys decide
+    Pending,
+    Action(Action),
+}
+
+pub struct Keymap {
+    // the first binding that matches wins
+    bindings: Vec<Binding>,
+    // the keys of an unfinished sequence
+    pending: Vec<KeyEvent>,
+    // keys of a sequence that didn't finish, they still need to be handled
+    unmatched: Vec<KeyEvent>,
+}
+
+impl Scope {
+    fn contains(&self, mode: Mode) -> bool {
+        match self {
+            Scope::Editor => matches!(mode, Mode::WriteMode | Mode::EditMode | Mode::HexMode),
+            Scope::Text => matches!(mode, Mode::WriteMode | Mode::EditMode),
+            Scope::Write => matches!(mode, Mode::WriteMode),
+            Scope::Edit => matches!(mode, Mode::EditMode),
+            Scope::Hex => matches!(mode, Mode::HexMode),
+        }
+    }
+
+    // whether a key could mean something in both scopes
+    fn overlaps(&self, other: Scope) -> bool {
+        [Mode::WriteMode, Mode::EditMode, Mode::HexMode]
+            .iter()
+            .any(|mode| self.contains(*mode) && other.contains(*mode))
+    }
+}
+
+impl Keymap {
+    pub fn new() -> Self {
+        Self::preset("default").unwrap()
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        let (_, bindings) = PRESETS.iter().find(|(preset, _)| *preset == name)?;
+        let bindings = bindings
+            .iter()
+            .map(|(scope, keys, action)| Binding {
+                scope: *scope,
+                keys: parse_keys(keys).unwrap(),
+                action: *action,
+            })
+            .collect();
+
+        Some(Self {
+            bindings,
+            pending: Vec::new(),
+            unmatched: Vec::new(),
+        })
+    }
+
+    // a binding from the config file, it replaces whatever the same keys did
+    // in that scope and "none" only removes the old one
+    pub fn bind(&mut self, scope: &str, keys: &str, action: &str) -> Result<(), String> {
+        let Some((scope, _)) = SCOPES.iter().find(|(_, name)| *name == scope) else {
+            return Err(format!("unknown key scope '{}'", scope));
+        };
+        let keys = parse_keys(keys)?;
+
+        self.bindings
+            .retain(|binding| binding.scope != *scope || binding.keys != keys);
+        if action == "none" {
+            return Ok(());
+        }
+
+        let Some(action) = actions::from_id(action) else {
+            return Err(format!("unknown action '{}'", action));
+        };
+        // bindings from the config file come before the preset's
+        self.bindings.insert(
+            0,
+            Binding {
+                scope: *scope,
+                keys,
+                action,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn press(&mut self, key_event: KeyEvent, mode: Mode) -> KeyPress {
+        self.pending.push(key_event);
+        let keys: Vec<Chord> = self.pending.iter().map(|key| normalize(*key)).collect();
+
+        let mut longer = false;
+        for binding in self
+            .bindings
+            .iter()
+            .filter(|binding| binding.scope.contains(mode))
+        {
+            if binding.keys == keys {
+                self.pending.clear();
+                return KeyPress::Action(binding.action);
+            }
+            longer |= binding.keys.starts_with(&keys);
+        }
+        if longer {
+            return KeyPress::Pending;
+        }
+
+        // a key that doesn't continue the sequence may still start another
+        // one, the keys before it weren't meant as a sequence after all
+        self.pending.pop();
+        let started = !self.pending.is_empty();
+        self.unmatched.append(&mut self.pending);
+        match started {
+            true => self.press(key_event, mode),
+            false => KeyPress::None,
+        }
+    }
+
+    // the keys of sequences that didn't finish, in the order they were pressed
+    pub fn take_unmatched(&mut self) -> Vec<KeyEvent> {
+        std::mem::take(&mut self.unmatched)
+    }
+
+    // a sequence doesn't go on in another mode
+    pub fn reset(&mut self) {
+        self.pending.clear();
+    }
+
+    // every key of an action, e.g. "F1, Ctrl+Shift+P"
+    pub fn describe_bindings(&self, action: Action) -> String {
+        self.bindings
+            .iter()
+            .filter(|binding| binding.action == action)
+            .map(|binding| describe_keys(&binding.keys))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    // the first keys of an action in a mode, for the help in the status bar
+    pub fn describe_binding(&self, action: Action, mode: Mode) -> Option<String> {
+        self.bindings
+            .iter()
+            .find(|binding| binding.action == action && binding.scope.contains(mode))
+            .map(|binding| describe_keys(&binding.keys))
+    }
+
+    // the keys typed so far of a sequence that isn't finished, if any
+    pub fn describe_pending(&self) -> Option<String> {
+        let keys: Vec<Chord> = self.pending.iter().map(|key| normalize(*key)).collect();
+        (!keys.is_empty()).then(|| describe_keys(&keys))
+    }
+
+    // keys bound twice in modes that overlap, and keys that finish before
+    // a longer sequence starting with them can be typed
+    pub fn conflicts(&self) -> Vec<String> {
+        let mut conflicts = Vec::new();
+
+        for (i, first) in self.bindings.iter().enumerate() {
+            for second in &self.bindings[i + 1..] {
+                if !first.scope.overlaps(second.scope) {
+                    continue;
+                }
+
+                let (short, long) = match first.keys.len() <= second.keys.len() {
+                    true => (first, second),
+                    false => (second, first),
+                };
+                if !long.keys.starts_with(&short.keys) || short.action == long.action {
+                    continue;
+                }
+
+                conflicts.push(match short.keys.len() == long.keys.len() {
+                    true => format!(
+                        "{} runs both {} and {}",
+                        describe_keys(&short.keys),
+                        actions::name_of(first.action),
+                        actions::name_of(second.action),
+                    ),
+                    false => format!(
+                        "{} ({}) keeps {} ({}) from being typed",
+                        describe_keys(&short.keys),
+                        actions::name_of(short.action),
+                        describe_keys(&long.keys),
+                        actions::name_of(long.action),
+                    ),
+                });
+            }
+        }
+
+        conflicts
+    }
+}
+
+pub fn preset_names() -> Vec<&'static str> {
+    PRESETS.iter().map(|(name, _)| *name).collect()
+}
+
+// "ctrl+s", "shift+n" or a sequence like "d d"
+fn parse_keys(text: &str) -> Result<Vec<Chord>, String> {
+    let keys: Vec<Chord> = text
+        .split_whitespace()
+        .map(parse_chord)
+        .collect::<Result<_, _>>()?;
+
+    match keys.is_empty() {
+        true => Err(String::from("empty key binding")),
+        false => Ok(keys),
+    }
+}
+
+fn parse_chord(text: &str) -> Result<Chord, String> {
+    // the last part is the key, so "ctrl++" binds the plus key
+    let (modifier_text, key) = match text.strip_suffix("++") {
+        Some(rest) => (rest, "+"),
+        None => match text.rsplit_once('+') {
+            Some((modifiers, key)) if !key.is_empty() => (modifiers, key),
+            _ => ("", text),
+        },
+    };
+
+    let mut modifiers = NONE;
+    for modifier in modifier_text.split('+').filter(|part| !part.is_empty()) {
+        modifiers |= match modifier.to_lowercase().as_str() {
+            "ctrl" | "control" => CONTROL,
+            "alt" => ALT,
+            "shift" => SHIFT,
+            _ => return Err(format!("unknown modifier '{}' in '{}'", modifier, text)),
+        };
+    }
+
+    let mut chars = key.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match key.to_lowercase().as_str() {
+            "esc" | "escape" => KeyCode::Esc,
+            "enter" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "del" | "delete" => KeyCode::Delete,
+            "space" => KeyCode::Char(' '),
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                Some(n @ 1..=12) => KeyCode::F(n),
+                _ => return Err(format!("unknown key '{}'", key)),
+            },
+        },
+    };
+
+    Ok(shift_letter(code, modifiers))
+}
+
+// terminals differ in whether shift comes with the upper case letter
+fn normalize(key_event: KeyEvent) -> Chord {
+    shift_letter(
+        key_event.code,
+        key_event.modifiers & (CONTROL | ALT | SHIFT),
+    )
+}
+
+fn shift_letter(code: KeyCode, mut modifiers: KeyModifiers) -> Chord {
+    let code = match code {
+        KeyCode::Char(c) if modifiers.contains(SHIFT) => {
+            modifiers.remove(SHIFT);
+            KeyCode::Char(c.to_ascii_uppercase())
+        }
+        code => code,
+    };
+    (code, modifiers)
+}
+
+fn describe_keys(keys: &[Chord]) -> String {
+    keys.iter()
+        .map(|(code, modifiers)| describe_key(*code, *modifiers))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn describe_key(code: KeyCode, modifiers: KeyModifiers) -> String {
+    let mut text = String::new();
+    if modifiers.contains(CONTROL) {
+        text.push_str("Ctrl+");
+    }
+    if modifiers.contains(ALT) {
+        text.push_str("Alt+");
+    }
+    if modifiers.contains(SHIFT) {
+        text.push_str("Shift+");
+    }
+
+    let key = match code {
+        KeyCode::Char(' ') => String::from("Space"),
+        KeyCode::Char(c) if c.is_ascii_uppercase() => "Shift+".to_owned() + &c.to_string(),
+        KeyCode::Char(c) => c.to_ascii_uppercase().to_string(),
+        KeyCode::F(n) => "F".to_owned() + &n.to_string(),
+        KeyCode::Esc => String::from("Esc"),
+        KeyCode::Enter => String::from("Enter"),
+        KeyCode::Tab => String::from("Tab"),
+        KeyCode::Backspace => String::from("Backspace"),
+        KeyCode::Delete => String::from("Del"),
+        KeyCode::Up => String::from("↑"),
+        KeyCode::Down => String::from("↓"),
+        KeyCode::Left => String::from("←"),
+        KeyCode::Right => String::from("→"),
+        KeyCode::Home => String::from("Home"),
+        KeyCode::End => String::from("End"),
+        KeyCode::PageUp => String::from("PgUp"),
+        KeyCode::PageDown => String::from("PgDn"),
+        _ => String::from("?"),
+    };
+    text + &key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    fn char(c: char) -> KeyEvent {
+        key(KeyCode::Char(c), NONE)
+    }
+
+    fn action(press: KeyPress) -> Option<Action> {
+        match press {
+            KeyPress::Action(action) => Some(action),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn parses_chords() {
+        assert_eq!(parse_chord("ctrl+s"), Ok((KeyCode::Char('s'), CONTROL)));
+        assert_eq!(parse_chord("shift+n"), Ok((KeyCode::Char('N'), NONE)));
+        assert_eq!(
+            parse_chord("Ctrl+Shift+P"),
+            Ok((KeyCode::Char('P'), CONTROL))
+        );
+        assert_eq!(parse_chord("ctrl++"), Ok((KeyCode::Char('+'), CONTROL)));
+        assert_eq!(parse_chord("f10"), Ok((KeyCode::F(10), NONE)));
+        assert_eq!(parse_chord("alt+space"), Ok((KeyCode::Char(' '), ALT)));
+        assert!(parse_chord("hyper+x").is_err());
+        assert!(parse_chord("f13").is_err());
+        assert!(parse_keys("").is_err());
+    }
+
+    #[test]
+    fn runs_single_keys_and_sequences() {
+        let mut keymap = Keymap::preset("vim").unwrap();
+
+        assert_eq!(
+            action(keymap.press(char('j'), Mode::EditMode)),
+            Some(Action::CursorDown)
+        );
+        assert!(matches!(
+            keymap.press(char('d'), Mode::EditMode),
+            KeyPress::Pending
+        ));
+        assert_eq!(
+            action(keymap.press(char('d'), Mode::EditMode)),
+            Some(Action::DeleteLine)
+        );
+
+        // shifted letters match with or without SHIFT being reported
+        let shifted = key(KeyCode::Char('N'), SHIFT);
+        assert_eq!(
+            action(keymap.press(shifted, Mode::EditMode)),
+            Some(Action::FindPrevious)
+        );
+
+        // the same key means something else in another mode
+        assert!(matches!(
+            keymap.press(char('j'), Mode::WriteMode),
+            KeyPress::None
+        ));
+    }
+
+    #[test]
+    fn hands_back_the_keys_of_an_unfinished_sequence() {
+        let mut keymap = Keymap::new();
+        keymap.bind("write", "j k", "edit_mode").unwrap();
+
+        assert!(matches!(
+            keymap.press(char('j'), Mode::WriteMode),
+            KeyPress::Pending
+        ));
+        assert!(keymap.take_unmatched().is_empty());
+        assert!(matches!(
+            keymap.press(char('x'), Mode::WriteMode),
+            KeyPress::None
+        ));
+        assert_eq!(keymap.take_unmatched(), vec![char('j')]);
+
+        // the key that broke the sequence can still run its own action
+        keymap.press(char('j'), Mode::WriteMode);
+        let save = key(KeyCode::Char('s'), CONTROL);
+        assert_eq!(
+            action(keymap.press(save, Mode::WriteMode)),
+            Some(Action::Save)
+        );
+        assert_eq!(keymap.take_unmatched(), vec![char('j')]);
+
+        keymap.press(char('j'), Mode::WriteMode);
+        keymap.reset();
+        assert!(matches!(
+            keymap.press(char('k'), Mode::WriteMode),
+            KeyPress::None
+        ));
+        assert!(keymap.take_unmatched().is_empty());
+    }
+
+    #[test]
+    fn describes_keys_for_the_status_bar() {
+        let mut keymap = Keymap::new();
+        assert_eq!(
+            keymap.describe_binding(Action::EditMode, Mode::WriteMode),
+            Some(String::from("Alt+J"))
+        );
+        assert_eq!(
+            keymap.describe_binding(Action::EditMode, Mode::EditMode),
+            None
+        );
+
+        keymap.bind("write", "j k", "edit_mode").unwrap();
+        assert_eq!(
+            keymap.describe_binding(Action::EditMode, Mode::WriteMode),
+            Some(String::from("J K"))
+        );
+
+        assert_eq!(keymap.describe_pending(), None);
+        keymap.press(char('j'), Mode::WriteMode);
+        assert_eq!(keymap.describe_pending(), Some(String::from("J")));
+        keymap.press(char('k'), Mode::WriteMode);
+        assert_eq!(keymap.describe_pending(), None);
+    }
+
+    #[test]
+    fn reports_conflicts() {
+        let mut keymap = Keymap::new();
+        assert!(keymap.conflicts().is_empty());
+
+        keymap.bind("text", "ctrl+g", "goto_line").unwrap();
+        keymap.bind("edit", "ctrl+g", "find").unwrap();
+        keymap.bind("edit", "u", "undo").unwrap();
+        keymap.bind("edit", "u u", "redo").unwrap();
+
+        let conflicts = keymap.conflicts();
+        assert_eq!(conflicts.len(), 2);
+        assert!(conflicts.contains(&String::from("Ctrl+G runs both Find and Go to line...")));
+        assert!(conflicts.contains(&String::from("U (Undo) keeps U U (Redo) from being typed")));
+    }
+}
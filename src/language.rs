@@ -0,0 +1,426 @@
+use crate::indent::leading_whitespace;
+
+// what the editor knows about a kind of file, picked by the file's extension
+pub struct Language {
+    pub name: &'static str,
+    pub extensions: &'static [&'static str],
+    // what comments start with, or start and end with
+    pub line_comment: Option<&'static str>,
+    pub block_comment: Option<(&'static str, &'static str)>,
+    // typing an opening bracket adds the closing one, lines ending in one
+    // indent the next line
+    pub brackets: &'static [(char, char)],
+    // closed right away like the brackets
+    pub quotes: &'static [char],
+    // whether 'x' is a char, brackets and quotes in one don't count
+    pub char_literals: bool,
+    // words or symbols at the end of a line that indent the next one
+    pub indent_after: &'static [&'static str],
+}
+
+const BRACKETS: &[(char, char)] = &[('(', ')'), ('[', ']'), ('{', '}')];
+
+pub const LANGUAGES: &[Language] = &[
+    Language {
+        name: "Plain Text",
+        extensions: &[],
+        line_comment: None,
+        block_comment: None,
+        brackets: BRACKETS,
+        quotes: &[],
+        char_literals: false,
+        indent_after: &[],
+    },
+    Language {
+        name: "Rust",
+        extensions: &["rs"],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        brackets: BRACKETS,
+        quotes: &['"'],
+        char_literals: true,
+        indent_after: &["=>"],
+    },
+    // the language of the syntax folder's kang2.yaml, which only has
+    // highlighting rules, so nothing here comes from it
+    Language {
+        name: "Kang",
+        extensions: &["kg"],
+        line_comment: Some("//"),
+        block_comment: None,
+        brackets: BRACKETS,
+        quotes: &['"'],
+        char_literals: false,
+        indent_after: &[],
+    },
+    Language {
+        name: "Python",
+        extensions: &["py", "pyw"],
+        line_comment: Some("#"),
+        block_comment: None,
+        brackets: BRACKETS,
+        quotes: &['"', '\''],
+        char_literals: false,
+        indent_after: &[":"],
+    },
+    Language {
+        name: "JavaScript",
+        extensions: &["js", "mjs", "cjs", "jsx", "ts", "tsx"],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        brackets: BRACKETS,
+        quotes: &['"', '\'', '`'],
+        char_literals: false,
+        indent_after: &["=>"],
+    },
+    Language {
+        name: "C",
+        extensions: &["c", "h"],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        brackets: BRACKETS,
+        quotes: &['"', '\''],
+        char_literals: true,
+        indent_after: &[],
+    },
+    Language {
+        name: "C++",
+        extensions: &["cpp", "hpp", "cc", "cxx"],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        brackets: BRACKETS,
+        quotes: &['"', '\''],
+        char_literals: true,
+        indent_after: &[],
+    },
+    Language {
+        name: "Java",
+        extensions: &["java"],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        brackets: BRACKETS,
+        quotes: &['"', '\''],
+        char_literals: true,
+        indent_after: &[],
+    },
+    Language {
+        name: "Go",
+        extensions: &["go"],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        brackets: BRACKETS,
+        quotes: &['"', '\'', '`'],
+        char_literals: true,
+        indent_after: &[],
+    },
+    Language {
+        name: "Lua",
+        extensions: &["lua"],
+        line_comment: Some("--"),
+        block_comment: Some(("--[[", "]]")),
+        brackets: BRACKETS,
+        quotes: &['"', '\''],
+        char_literals: false,
+        indent_after: &["then", "do", "else", "repeat"],
+    },
+    Language {
+        name: "Shell",
+        extensions: &["sh", "bash", "zsh"],
+        line_comment: Some("#"),
+        block_comment: None,
+        brackets: BRACKETS,
+        quotes: &['"', '\''],
+        char_literals: false,
+        indent_after: &["then", "do", "else", "in"],
+    },
+    Language {
+        name: "YAML",
+        extensions: &["yaml", "yml"],
+        line_comment: Some("#"),
+        block_comment: None,
+        brackets: BRACKETS,
+        quotes: &['"', '\''],
+        char_literals: false,
+        indent_after: &[":"],
+    },
+    Language {
+        name: "TOML",
+        extensions: &["toml"],
+        line_comment: Some("#"),
+        block_comment: None,
+        brackets: BRACKETS,
+        quotes: &['"', '\''],
+        char_literals: false,
+        indent_after: &[],
+    },
+    // the only comments are block comments
+    Language {
+        name: "CSS",
+        extensions: &["css"],
+        line_comment: None,
+        block_comment: Some(("/*", "*/")),
+        brackets: BRACKETS,
+        quotes: &['"', '\''],
+        char_literals: false,
+        indent_after: &[],
+    },
+    Language {
+        name: "HTML",
+        extensions: &["html", "htm", "xml", "svg"],
+        line_comment: None,
+        block_comment: Some(("<!--", "-->")),
+        brackets: BRACKETS,
+        quotes: &['"'],
+        char_literals: false,
+        indent_after: &[],
+    },
+    Language {
+        name: "JSON",
+        extensions: &["json"],
+        line_comment: None,
+        block_comment: None,
+        brackets: BRACKETS,
+        quotes: &['"'],
+        char_literals: false,
+        indent_after: &[],
+    },
+];
+
+// files with an unknown extension are plain text
+pub fn language_of(file_name: &str) -> &'static Language {
+    let extension = match file_name.rsplit_once('.') {
+        Some((_, extension)) => extension.to_lowercase(),
+        None => return &LANGUAGES[0],
+    };
+
+    LANGUAGES
+        .iter()
+        .find(|language| language.extensions.contains(&extension.as_str()))
+        .unwrap_or(&LANGUAGES[0])
+}
+
+// a language by what `set language=` takes, its name without spaces or one
+// of its extensions, in any case
+pub fn find_language(name: &str) -> Option<&'static Language> {
+    let name = name.to_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|language| language.id() == name || language.extensions.contains(&name.as_str()))
+}
+
+impl Language {
+    // the name in lower case without spaces, like "plaintext"
+    pub fn id(&self) -> String {
+        self.name.to_lowercase().replace(' ', "")
+    }
+
+    // whether the line after this one gets one more level of indentation
+    pub fn indents_after(&self, line: &str) -> bool {
+        let line = line.trim_end();
+
+        if let Some(last) = line.chars().last() {
+            if self.brackets.iter().any(|(open, _)| *open == last) {
+                return true;
+            }
+        }
+
+        // words only count on their own, `undo` doesn't end in `do`
+        self.indent_after.iter().any(|token| {
+            line.strip_suffix(token).is_some_and(|rest| {
+                !token.chars().all(char::is_alphanumeric)
+                    || !rest.ends_with(|c: char| c.is_alphanumeric() || c == '_')
+            })
+        })
+    }
+
+    // what gets typed along with an opening bracket or a quote
+    pub fn closing_of(&self, open: char) -> Option<char> {
+        if self.quotes.contains(&open) {
+            return Some(open);
+        }
+
+        self.brackets
+            .iter()
+            .find(|(o, _)| *o == open)
+            .map(|(_, close)| *close)
+    }
+
+    // the closing char to insert along with `c` typed between `previous` and
+    // `next`, nothing in front of a word and no quote right after one
+    pub fn auto_close(&self, c: char, previous: Option<char>, next: Option<char>) -> Option<char> {
+        let close = self.closing_of(c)?;
+
+        let free = next.is_none_or(|n| n.is_whitespace() || self.is_closing(n));
+        let after_word = self.quotes.contains(&c)
+            && previous.is_some_and(|p| p.is_alphanumeric() || p == '_' || p == c);
+
+        (free && !after_word).then_some(close)
+    }
+
+    // closing brackets and quotes get stepped over when typed in front of
+    // the same char
+    pub fn is_closing(&self, c: char) -> bool {
+        self.opening_of(c).is_some() || self.quotes.contains(&c)
+    }
+
+    // the line commented out or back in and how many chars the text moved,
+    // the comment goes after the indentation so it stays aligned. only the
+    // token with a space after it counts, so `/// doc` gets commented instead
+    // of losing a slash. none without comments in the language
+    pub fn toggle_comment(&self, line: &str) -> Option<(String, isize)> {
+        self.uncomment(line).or_else(|| self.comment(line))
+    }
+
+    // the lines all commented out, or all back in when every line with text
+    // is commented already, like toggle_comment
+    pub fn toggle_comments(&self, lines: &[String]) -> Option<Vec<(String, isize)>> {
+        let uncommented: Option<Vec<_>> = lines
+            .iter()
+            .map(|line| match line.trim().is_empty() {
+                true => Some((line.clone(), 0)),
+                false => self.uncomment(line),
+            })
+            .collect();
+        if uncommented.is_some() && lines.iter().any(|line| !line.trim().is_empty()) {
+            return uncommented;
+        }
+
+        lines.iter().map(|line| self.comment(line)).collect()
+    }
+
+    fn comment(&self, line: &str) -> Option<(String, isize)> {
+        let indent = leading_whitespace(line);
+        let text = &line[indent.len()..];
+        if text.is_empty() {
+            return (self.line_comment.is_some() || self.block_comment.is_some())
+                .then(|| (line.to_owned(), 0));
+        }
+
+        if let Some(token) = self.line_comment {
+            let added = token.chars().count() as isize + 1;
+            return Some((format!("{}{} {}", indent, token, text), added));
+        }
+
+        let (start, end) = self.block_comment?;
+        let added = start.chars().count() as isize + 1;
+        Some((format!("{}{} {} {}", indent, start, text, end), added))
+    }
+
+    // none when the line isn't commented
+    fn uncomment(&self, line: &str) -> Option<(String, isize)> {
+        let indent = leading_whitespace(line);
+        let text = &line[indent.len()..];
+
+        if let Some(token) = self.line_comment {
+            let removed = token.chars().count() as isize + 1;
+            let rest = text.strip_prefix(token)?;
+            return match rest.strip_prefix(' ') {
+                Some(rest) => Some((indent.to_owned() + rest, -removed)),
+                None if rest.is_empty() => Some((indent.to_owned(), 1 - removed)),
+                None => None,
+            };
+        }
+
+        let (start, end) = self.block_comment?;
+        let removed = start.chars().count() as isize + 1;
+        let inner = text
+            .strip_prefix(start)?
+            .strip_prefix(' ')?
+            .strip_suffix(end)?
+            .strip_suffix(' ')?;
+        Some((indent.to_owned() + inner, -removed))
+    }
+
+    // plain text is the only language without extensions, brackets in it
+    // are often unbalanced on purpose, like "1)" lists
+    pub fn is_plain_text(&self) -> bool {
+        self.extensions.is_empty()
+    }
+
+    // the opening bracket a closing one belongs to
+    pub fn opening_of(&self, close: char) -> Option<char> {
+        self.brackets
+            .iter()
+            .find(|(_, c)| *c == close)
+            .map(|(open, _)| *open)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn toggle(file_name: &str, line: &str) -> Option<String> {
+        language_of(file_name)
+            .toggle_comment(line)
+            .map(|(toggled, _)| toggled)
+    }
+
+    #[test]
+    fn toggles_line_comments_after_the_indentation() {
+        assert_eq!(
+            toggle("a.rs", "    let a = 1;").unwrap(),
+            "    // let a = 1;"
+        );
+        assert_eq!(
+            toggle("a.rs", "    // let a = 1;").unwrap(),
+            "    let a = 1;"
+        );
+        assert_eq!(toggle("a.py", "\tx = 1").unwrap(), "\t# x = 1");
+        assert_eq!(toggle("a.rs", "//").unwrap(), "");
+        assert_eq!(toggle("a.rs", "").unwrap(), "");
+    }
+
+    #[test]
+    fn comments_lines_that_only_start_like_a_comment() {
+        assert_eq!(toggle("a.rs", "/// doc").unwrap(), "// /// doc");
+        assert_eq!(toggle("a.rs", "// /// doc").unwrap(), "/// doc");
+        assert_eq!(toggle("a.rs", "//! doc").unwrap(), "// //! doc");
+    }
+
+    #[test]
+    fn toggles_block_comments() {
+        assert_eq!(
+            toggle("a.css", "  a { b: c; }").unwrap(),
+            "  /* a { b: c; } */"
+        );
+        assert_eq!(
+            toggle("a.css", "  /* a { b: c; } */").unwrap(),
+            "  a { b: c; }"
+        );
+        assert_eq!(toggle("a.css", "/** doc */").unwrap(), "/* /** doc */ */");
+        assert_eq!(toggle("a.html", "<p>").unwrap(), "<!-- <p> -->");
+        assert_eq!(toggle("a.html", "<!-- <p> -->").unwrap(), "<p>");
+    }
+
+    #[test]
+    fn toggles_ranges_together() {
+        let rust = language_of("a.rs");
+        let lines = |text: &str| text.split('|').map(String::from).collect::<Vec<_>>();
+        let toggled = |text: &str| -> Vec<String> {
+            let toggled = rust.toggle_comments(&lines(text)).unwrap();
+            toggled.into_iter().map(|(line, _)| line).collect()
+        };
+
+        assert_eq!(toggled("a|  b||c"), lines("// a|  // b||// c"));
+        assert_eq!(toggled("// a|  // b||// c"), lines("a|  b||c"));
+        // a line that isn't commented yet comments all of them
+        assert_eq!(toggled("// a|b"), lines("// // a|// b"));
+        assert_eq!(toggled("|"), lines("|"));
+        assert!(language_of("a.json").toggle_comments(&lines("a")).is_none());
+    }
+
+    #[test]
+    fn reports_how_far_the_text_moved() {
+        let rust = language_of("a.rs");
+        assert_eq!(rust.toggle_comment("  a").unwrap().1, 3);
+        assert_eq!(rust.toggle_comment("  // a").unwrap().1, -3);
+        assert_eq!(language_of("a.html").toggle_comment("a").unwrap().1, 5);
+    }
+
+    #[test]
+    fn has_nothing_to_toggle_without_comments() {
+        assert_eq!(toggle("a.json", "{}"), None);
+        assert_eq!(toggle("a.txt", "text"), None);
+    }
+}
@@ -1,7 +1,7 @@
 use crossterm::cursor::{Hide, MoveTo, Show};
 use crossterm::event::{
-    read, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEvent,
-    MouseEventKind,
+    poll, read, DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture,
+    Event, KeyCode, KeyEventKind, MouseButton, MouseEvent, MouseEventKind,
 };
 use crossterm::execute;
 use crossterm::style::{StyledContent, Stylize};
@@ -10,27 +10,77 @@ use functions::{clear, move_to, purge};
 use terminal_link::Link;
 
 use std::env;
-use std::fs::read_to_string;
 use std::io::{self, Write};
-
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+mod actions;
+mod brackets;
+mod buffer;
+mod command_history;
+mod commands;
+mod config;
 mod console;
+mod diff;
 mod editmode;
+mod encoding;
+mod explorer;
+mod file_finder;
 mod format;
 mod functions;
+mod hexmode;
+mod history;
+mod indent;
+mod keymap;
+mod language;
 mod menu;
+mod palette;
+mod project_search;
+mod prompt;
+mod replace;
+mod search;
+mod settings;
 mod skeleton;
+mod swap;
+mod theme;
 mod writemode;
 
+use actions::Action;
+use brackets::Brackets;
+use buffer::Buffer;
+use commands::CommandResult;
+use config::Config;
 use console::{Console, ConsoleAction};
+use diff::DiffView;
+use explorer::{Explorer, ExplorerAction};
+use file_finder::FileFinder;
 use format::format;
 use functions::*;
-use menu::Menu;
-
+use indent::{char_at_column, expand_tabs};
+use keymap::{KeyPress, Keymap};
+use menu::{Menu, MenuAction};
+use palette::Palette;
+use project_search::ProjectSearch;
+use prompt::{Prompt, PromptAction};
+use replace::{Replace, ReplaceMode};
+use search::Search;
+use settings::Settings;
+use swap::Swap;
+
+#[derive(Clone, Copy, PartialEq)]
 pub enum Mode {
     WriteMode,
     EditMode,
     MenuMode,
     ConsoleMode,
+    PromptMode,
+    HexMode,
+    DiffMode,
+    FindMode,
+    ResultsMode,
+    FinderMode,
+    ExplorerMode,
+    PaletteMode,
 }
 
 pub enum ChangedLineType {
@@ -47,10 +97,7 @@ fn main() -> io::Result<()> {
     execute!(io::stdout(), Hide)?;
 
     let args: Vec<String> = env::args().collect();
-    let mut lines: Vec<String>;
     let mut cached_lines: Vec<Vec<StyledContent<String>>> = Vec::new();
-    let mut file_name: String;
-    let mut file_path: String;
     let mut info_text = String::new();
 
     // println!(
@@ -58,16 +105,16 @@ fn main() -> io::Result<()> {
     //     env::current_dir().unwrap().display().to_string() + &args[1]
     // );
 
-    if args.len() > 1 {
-        file_path = env::current_dir().unwrap().display().to_string() + "/" + &args[1];
-        let file = read_to_string(&file_path)?;
-        lines = file.lines().map(|s| s.to_string()).collect();
-        file_name = args[1].clone();
-    } else {
-        lines = vec!["".to_string()];
-        file_name = String::from("");
-        file_path = env::current_dir().unwrap().display().to_string() + "/new_file.txt";
-    }
+    // no file open yet, shows the start screen
+    let mut buffer = Buffer::new(
+        String::from(""),
+        env::current_dir()
+            .unwrap()
+            .join("new_file.txt")
+            .display()
+            .to_string(),
+        vec!["".to_string()],
+    );
 
     // print!("{:?}", lines);
 
@@ -81,66 +128,1230 @@ fn main() -> io::Result<()> {
 
     let mut menu = Menu::new();
     let mut console = Console::new();
+    let mut prompt = Prompt::new();
+    let mut diff_view = DiffView::new();
+    let mut search = Search::new();
+    let mut replacing: Option<Replace> = None;
+    let mut results = ProjectSearch::new();
+    let mut finder = FileFinder::new();
+    let mut explorer = Explorer::new();
+    let mut palette = Palette::new();
+    let mut settings = Settings::new();
+    let mut config = Config::new();
+    let mut keymap = Keymap::new();
+    let mut swap = Swap::new();
+    // scanned again only after the text changed
+    let mut bracket_cache: Option<Brackets> = None;
+    // closers typed along with their opening bracket or quote, as line and
+    // char, typing them again steps over them
+    let mut auto_closed: Vec<(usize, usize)> = Vec::new();
 
     let mut initial = true;
+    let mut last_input = Instant::now();
     let mut current_mode = Mode::WriteMode;
     let mut term_size = size().unwrap();
 
-    let save_file_as = |lines: &Vec<String>, name: &str| -> io::Result<String> {
-        clear()?;
-        std::fs::write(
-            env::current_dir().unwrap().display().to_string() + "/" + &name,
-            lines.clone().join("\n"),
-        )?;
-
-        Ok("File saved as '".to_owned() + name + "'")
-    };
-
     execute!(io::stdout(), MoveTo(0, 0))?;
     execute!(io::stdout(), EnableMouseCapture)?;
+    execute!(io::stdout(), EnableFocusChange)?;
+
+    macro_rules! quit {
+        () => {
+            swap.remove(&buffer);
+            clear()?;
+            for_real_clear()?;
+            move_to(0, 0)?;
+            break;
+        };
+    }
+
+    // evaluates to whether the file actually got written
+    macro_rules! save_file {
+        ($result:expr) => {
+            match $result {
+                Ok(()) => {
+                    info_text = "File saved as '".to_owned() + &buffer.file_name + "'";
+                    swap.remove(&buffer);
+                    true
+                }
+                Err(error) => {
+                    info_text = "Could not save: ".to_owned() + &describe_error(&error);
+                    false
+                }
+            }
+        };
+    }
+
+    // a file changed by another program isn't overwritten without asking,
+    // the action waiting for the save is asked about along with it
+    macro_rules! save_buffer {
+        ($then:expr) => {
+            if buffer.changed_on_disk() {
+                prompt.open_external_change(
+                    &buffer.file_name,
+                    buffer.hex.is_none(),
+                    true,
+                    $then,
+                    current_mode,
+                );
+                current_mode = Mode::PromptMode;
+                false
+            } else {
+                save_file!(buffer.save())
+            }
+        };
+    }
+
+    // buffers holding raw bytes are always edited in hex mode
+    macro_rules! sync_hex_mode {
+        () => {
+            if buffer.hex.is_some() {
+                current_mode = Mode::HexMode;
+            } else if matches!(current_mode, Mode::HexMode) {
+                current_mode = Mode::WriteMode;
+            }
+        };
+    }
+
+    macro_rules! new_file {
+        () => {
+            swap.remove(&buffer);
+            buffer = Buffer::create(Path::new(&buffer.file_path).with_file_name("new_file.txt"));
+            current_line = 0;
+            current_char = 0;
+            current_scroll = 0;
+            buffer.indent = settings.default_indent();
+            bracket_cache = None;
+            auto_closed.clear();
+            sync_hex_mode!();
+        };
+    }
+
+    // opens a path into the buffer, problems end up in the status bar
+    macro_rules! load_file {
+        ($file_path:expr) => {
+            let file_path: PathBuf = $file_path;
+            swap.remove(&buffer);
+
+            match Buffer::open(file_path.clone()) {
+                Ok(opened) => {
+                    buffer = opened;
+                    info_text = match buffer.hex {
+                        Some(_) => String::from("Binary file, opened in hex mode"),
+                        None => String::new(),
+                    };
+                }
+                Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                    buffer = Buffer::create(file_path);
+                    info_text = String::from("New file, it will be created on save");
+                }
+                Err(error) => {
+                    info_text = "Could not open '".to_owned()
+                        + &file_path.display().to_string()
+                        + "': "
+                        + &describe_error(&error);
+                }
+            }
+
+            current_line = 0;
+            current_char = 0;
+            current_scroll = 0;
+            buffer.detect_indent(settings.default_indent());
+            bracket_cache = None;
+            auto_closed.clear();
+            sync_hex_mode!();
+
+            if swap::find_leftover(&buffer).is_some() {
+                prompt.open(
+                    "Found unsaved changes to '".to_owned()
+                        + &buffer.file_name
+                        + "' from a session that didn't exit cleanly.",
+                    vec![
+                        ('r', "Recover"),
+                        ('d', "Delete them"),
+                        ('k', "Keep for later"),
+                    ],
+                    PromptAction::RecoverSwap,
+                    current_mode,
+                );
+                current_mode = Mode::PromptMode;
+            }
+        };
+    }
+
+    // asks about the next match of a confirm-each replace, or finishes it
+    macro_rules! replace_next {
+        () => {
+            if let Some(replace) = &mut replacing {
+                match replace.next(&buffer.lines) {
+                    Some((line, char)) => {
+                        current_line = line;
+                        current_char = char;
+                        jump_to_editor_point(
+                            &mut current_line,
+                            &mut current_scroll,
+                            &calculate_editor_height(&(term_size.1 as usize)),
+                        );
+
+                        prompt.open(
+                            format!(
+                                "Replace this match? ({} left)",
+                                replace.count(&buffer.lines)
+                            ),
+                            vec![('y', "Yes"), ('n', "No"), ('a', "All"), ('q', "Quit")],
+                            PromptAction::ReplaceMatch,
+                            current_mode,
+                        );
+                        current_mode = Mode::PromptMode;
+                    }
+                    None => {
+                        info_text = replace.summary();
+                        replacing = None;
+                    }
+                }
+            }
+        };
+    }
+
+    // the first replacement of a command saves the lines for undo,
+    // so the whole command is undone at once
+    macro_rules! record_replace {
+        ($replace:expr) => {
+            if $replace.replaced == 0 {
+                buffer
+                    .history
+                    .record(buffer.lines.clone(), current_line, current_char, false);
+            }
+        };
+    }
+
+    // jumps to the selected project search hit, opening its file if needed
+    macro_rules! open_result {
+        () => {
+            if let Some(hit) = results.selected() {
+                let (path, line, char) = (hit.path.clone(), hit.line, hit.char);
+
+                if Path::new(&buffer.file_path) != path {
+                    load_file!(path);
+                }
+                if buffer.hex.is_none() {
+                    current_line = line;
+                    current_char = char;
+                    fit_cursor(&mut current_line, &mut current_char, &buffer.lines);
+                    jump_to_editor_point(
+                        &mut current_line,
+                        &mut current_scroll,
+                        &calculate_editor_height(&(term_size.1 as usize)),
+                    );
+                }
+            }
+        };
+    }
+
+    macro_rules! open_file {
+        () => {
+            finder.open(env::current_dir().unwrap(), current_mode);
+            current_mode = Mode::FinderMode;
+        };
+    }
+
+    // what the unsaved changes prompt asked about, once it's fine to do
+    macro_rules! run_confirmed {
+        ($action:expr) => {
+            match $action {
+                PromptAction::Quit => {
+                    quit!();
+                }
+                PromptAction::NewFile => {
+                    new_file!();
+                }
+                PromptAction::OpenFile => {
+                    open_file!();
+                }
+                PromptAction::OpenResult => {
+                    open_result!();
+                }
+                PromptAction::OpenEntry(path) => {
+                    load_file!(path);
+                }
+                _ => {}
+            }
+        };
+    }
+
+    // everything bound in keymap.rs and listed in the command palette ends up here
+    macro_rules! run_action {
+        ($action:expr) => {
+            let editor_height = &calculate_editor_height(&(term_size.1 as usize));
+            let editing = matches!(current_mode, Mode::WriteMode | Mode::EditMode);
+            if $action.edits_text() {
+                bracket_cache = None;
+            }
+
+            match $action {
+                Action::Quit => {
+                    if !buffer.is_modified() {
+                        quit!();
+                    }
+
+                    prompt.open_unsaved(&buffer.file_name, PromptAction::Quit, current_mode);
+                    current_mode = Mode::PromptMode;
+                }
+                Action::Save => {
+                    save_buffer!(None);
+                }
+                Action::SaveAs => {
+                    console.open_with(ConsoleAction::SaveAs, "Save as: ");
+                    current_mode = Mode::ConsoleMode;
+                }
+                Action::NewFile => {
+                    if buffer.is_modified() {
+                        prompt.open_unsaved(&buffer.file_name, PromptAction::NewFile, current_mode);
+                        current_mode = Mode::PromptMode;
+                    } else {
+                        new_file!();
+                    }
+                }
+                Action::OpenFile => {
+                    if buffer.is_modified() {
+                        prompt.open_unsaved(
+                            &buffer.file_name,
+                            PromptAction::OpenFile,
+                            current_mode,
+                        );
+                        current_mode = Mode::PromptMode;
+                    } else {
+                        open_file!();
+                    }
+                }
+                Action::ToggleHex => {
+                    if buffer.file_name != "" {
+                        let result = match buffer.hex {
+                            Some(_) => buffer.leave_hex(),
+                            None => buffer.enter_hex(),
+                        };
+
+                        match result {
+                            Ok(()) => {
+                                fit_cursor(&mut current_line, &mut current_char, &buffer.lines);
+                                sync_hex_mode!();
+                            }
+                            Err(error) => info_text = describe_error(&error),
+                        }
+                    }
+                }
+                Action::ToggleExplorer => {
+                    // opening the tree shows where the current file is
+                    let showing = !explorer.visible;
+                    explorer.focus(env::current_dir().unwrap(), current_mode);
+                    if showing {
+                        explorer.reveal(Path::new(&buffer.file_path), editor_height);
+                    }
+                    current_mode = Mode::ExplorerMode;
+                }
+                Action::OpenMenu(i) => {
+                    menu.open(i, current_mode);
+                    current_mode = Mode::MenuMode;
+                }
+                Action::Theme(i) => {
+                    theme::set_theme(theme::THEMES[i]);
+                    info_text = "Theme set to ".to_owned() + theme::current().name;
+                }
+                Action::ToggleSaveOnUnfocus => {
+                    settings.save_on_unfocus = !settings.save_on_unfocus;
+                    info_text = match settings.save_on_unfocus {
+                        true => String::from("Saving when the terminal loses focus"),
+                        false => String::from("Not saving when the terminal loses focus"),
+                    };
+                }
+                Action::CommandPalette => {
+                    palette.open(current_mode);
+                    current_mode = Mode::PaletteMode;
+                }
+                Action::OpenConsole => {
+                    console.open(ConsoleAction::Command);
+                    current_mode = Mode::ConsoleMode;
+                }
+                // the commands that need more input start out half typed
+                Action::Replace
+                | Action::SearchFiles
+                | Action::GotoLine
+                | Action::SetOption
+                | Action::SetLanguage => {
+                    console.open_command(match $action {
+                        Action::Replace => "replace /",
+                        Action::SearchFiles => "search ",
+                        Action::SetOption => "set ",
+                        Action::SetLanguage => "set language=",
+                        _ => "goto ",
+                    });
+                    current_mode = Mode::ConsoleMode;
+                }
+                Action::Find => {
+                    if editing && !buffer.file_name.is_empty() {
+                        search.open(
+                            current_mode,
+                            &buffer.lines,
+                            &mut current_line,
+                            &mut current_char,
+                        );
+                        current_mode = Mode::FindMode;
+                    }
+                }
+                Action::FindNext | Action::FindPrevious => {
+                    let forward = $action == Action::FindNext;
+                    search.jump(forward, &buffer.lines, &mut current_line, &mut current_char);
+                    info_text = search.describe();
+                }
+                Action::Undo | Action::Redo => {
+                    if editing {
+                        let history = &mut buffer.history;
+                        let lines = &mut buffer.lines;
+                        let undo = $action == Action::Undo;
+                        let done = match undo {
+                            true => history.undo(lines, &mut current_line, &mut current_char),
+                            false => history.redo(lines, &mut current_line, &mut current_char),
+                        };
+
+                        if done {
+                            jump_to_editor_point(
+                                &mut current_line,
+                                &mut current_scroll,
+                                editor_height,
+                            );
+                        } else {
+                            info_text = match undo {
+                                true => String::from("Nothing to undo"),
+                                false => String::from("Nothing to redo"),
+                            };
+                        }
+                    }
+                }
+                Action::MatchBracket => {
+                    if editing {
+                        let brackets = bracket_cache
+                            .get_or_insert_with(|| Brackets::scan(&buffer.lines, buffer.language));
+                        match brackets.partner(current_line, current_char) {
+                            Some((line, char)) => {
+                                current_line = line;
+                                current_char = char;
+                                jump_to_editor_point(
+                                    &mut current_line,
+                                    &mut current_scroll,
+                                    editor_height,
+                                );
+                            }
+                            None => info_text = String::from("No matching bracket at the cursor"),
+                        }
+                    }
+                }
+                Action::ToggleComment => {
+                    if editing {
+                        let line = &buffer.lines[current_line];
+                        match buffer.language.toggle_comment(line) {
+                            Some((toggled, moved)) if toggled != *line => {
+                                let before = (buffer.lines.clone(), current_line, current_char);
+                                // the cursor stays on the same text
+                                current_char = current_char
+                                    .saturating_add_signed(moved)
+                                    .min(char_count(&toggled));
+                                buffer.lines[current_line] = toggled;
+                                buffer.history.record(before.0, before.1, before.2, false);
+                            }
+                            Some(_) => {}
+                            None => {
+                                info_text = buffer.language.name.to_owned() + " has no comments"
+                            }
+                        }
+                    }
+                }
+                Action::EditMode => {
+                    if editing {
+                        current_mode = Mode::EditMode;
+                    }
+                }
+                // moving the cursor and lines only makes sense on text
+                action => {
+                    if editing {
+                        // cursor movements don't need the lines saved for undo
+                        let before = action.edits_text().then(|| buffer.lines.clone());
+                        let position = (current_line, current_char);
+                        editmode::run_action(
+                            action,
+                            &mut current_line,
+                            &mut current_char,
+                            &mut current_scroll,
+                            editor_height,
+                            &mut current_mode,
+                            &mut buffer.lines,
+                            buffer.indent,
+                        )?;
+
+                        if let Some(before) = before.filter(|before| *before != buffer.lines) {
+                            buffer.history.record(before, position.0, position.1, false);
+                        }
+                    }
+                }
+            }
+        };
+    }
+
+    if args.len() > 1 {
+        load_file!(env::current_dir().unwrap().join(&args[1]));
+    }
+
+    // mistakes in the config file show up right away instead of being ignored
+    let config_errors = config.load(&mut settings, &mut keymap);
+    if !config_errors.is_empty() {
+        info_text = describe_config_errors(&config_errors);
+    }
 
     loop {
         let mut changed_line = ChangedLineType::None;
+        let mode_before = current_mode;
+
+        let idle = initial || !poll(Duration::from_millis(500))?;
+
+        if idle {
+            // nothing to read, a good moment for the background work
+            if config.changed() {
+                let errors = config.load(&mut settings, &mut keymap);
+                // a changed default indent applies to files without their own
+                if buffer.default_indent {
+                    buffer.indent = settings.default_indent();
+                }
+                info_text = match errors.is_empty() {
+                    true => String::from("Config reloaded"),
+                    false => describe_config_errors(&errors),
+                };
+                changed_line = ChangedLineType::All;
+                clear()?;
+            }
 
-        if matches!(current_mode, Mode::ConsoleMode) {
+            if let Err(error) = swap.update(&buffer, settings.swap_interval) {
+                info_text = "Could not write swap file: ".to_owned() + &describe_error(&error);
+                changed_line = ChangedLineType::Skeleton;
+            }
+
+            let idle_time = Duration::from_secs(settings.autosave_after);
+            if settings.autosave_after > 0
+                && last_input.elapsed() >= idle_time
+                && buffer.is_modified()
+                && Path::new(&buffer.file_path).exists()
+                && matches!(
+                    current_mode,
+                    Mode::WriteMode | Mode::EditMode | Mode::HexMode
+                )
+            {
+                save_buffer!(None);
+                changed_line = ChangedLineType::All;
+            }
+        } else if matches!(current_mode, Mode::ConsoleMode) {
             if let Ok(event) = read() {
                 if let Event::Key(key_event) = event {
-                    if (key_event.kind != KeyEventKind::Press
-                        || key_event.kind != KeyEventKind::Repeat)
-                        && !initial
-                    {
+                    if key_event.kind != KeyEventKind::Press && !initial {
                         continue;
                     }
                     match key_event.code {
                         KeyCode::Enter => {
                             let result = console.submit();
-                            match *console.get_action() {
-                                ConsoleAction::SaveAs => info_text = save_file_as(&lines, &result)?,
+                            // commands like replace or reopen change the text
+                            bracket_cache = None;
+                            current_mode = console.get_return_mode();
+                            let action = console.get_action().clone();
+                            match &action {
+                                ConsoleAction::SaveAs => {
+                                    let file_path = env::current_dir().unwrap().join(&result);
+                                    save_file!(buffer.save_as(file_path));
+                                }
+                                ConsoleAction::Command => {
+                                    match commands::run_command(&result, &mut buffer, &mut settings)
+                                    {
+                                        Ok(CommandResult::Message(message)) => info_text = message,
+                                        Ok(CommandResult::Replace(mut replace)) => {
+                                            match replace.mode {
+                                                ReplaceMode::One => {
+                                                    // the next match after the cursor, wrapping around
+                                                    replace.start_at(
+                                                        &buffer.lines,
+                                                        current_line,
+                                                        current_char,
+                                                    );
+                                                    let found =
+                                                        replace.next(&buffer.lines).or_else(|| {
+                                                            replace.start_at(&buffer.lines, 0, 0);
+                                                            replace.next(&buffer.lines)
+                                                        });
+
+                                                    // nothing to undo without a match
+                                                    if found.is_some() {
+                                                        record_replace!(replace);
+                                                    }
+                                                    if let Some((line, char)) =
+                                                        replace.replace_current(&mut buffer.lines)
+                                                    {
+                                                        current_line = line;
+                                                        current_char = char;
+                                                    }
+                                                    info_text = replace.summary();
+                                                }
+                                                ReplaceMode::All => {
+                                                    let count = replace.count(&buffer.lines);
+                                                    if count == 0 {
+                                                        info_text = replace.summary();
+                                                    } else {
+                                                        prompt.open(
+                                                            format!("Replace {} matches?", count),
+                                                            vec![('r', "Replace"), ('c', "Cancel")],
+                                                            PromptAction::ReplaceAll,
+                                                            current_mode,
+                                                        );
+                                                        current_mode = Mode::PromptMode;
+                                                        replacing = Some(replace);
+                                                    }
+                                                }
+                                                ReplaceMode::Confirm => {
+                                                    replacing = Some(replace);
+                                                    replace_next!();
+                                                }
+                                            }
+                                        }
+                                        Ok(CommandResult::Search(found)) => {
+                                            results = found;
+                                            if results.is_empty() {
+                                                info_text = String::from("No matches found");
+                                            } else {
+                                                current_mode = Mode::ResultsMode;
+                                            }
+                                        }
+                                        Ok(CommandResult::Save { path, quit }) => {
+                                            let saved = match path {
+                                                Some(path) => save_file!(buffer.save_as(path)),
+                                                None => save_buffer!(match quit {
+                                                    true => Some(PromptAction::Quit),
+                                                    false => None,
+                                                }),
+                                            };
+                                            if saved && quit {
+                                                quit!();
+                                            }
+                                        }
+                                        Ok(CommandResult::Quit { force }) => {
+                                            if force || !buffer.is_modified() {
+                                                quit!();
+                                            }
+                                            prompt.open_unsaved(
+                                                &buffer.file_name,
+                                                PromptAction::Quit,
+                                                current_mode,
+                                            );
+                                            current_mode = Mode::PromptMode;
+                                        }
+                                        Ok(CommandResult::Open(path)) => {
+                                            // reopening the same file would drop the
+                                            // changes just as well
+                                            if buffer.is_modified() {
+                                                prompt.open_unsaved(
+                                                    &buffer.file_name,
+                                                    PromptAction::OpenEntry(path),
+                                                    current_mode,
+                                                );
+                                                current_mode = Mode::PromptMode;
+                                            } else {
+                                                load_file!(path);
+                                            }
+                                        }
+                                        Ok(CommandResult::Goto(line)) => {
+                                            current_line = line;
+                                            current_char = 0;
+                                            jump_to_editor_point(
+                                                &mut current_line,
+                                                &mut current_scroll,
+                                                &calculate_editor_height(&(term_size.1 as usize)),
+                                            );
+                                        }
+                                        Ok(CommandResult::Indent(lines, outdent)) => {
+                                            let before = buffer.lines.clone();
+                                            let position = (current_line, current_char);
+                                            for (i, line) in buffer.lines[lines.0..=lines.1]
+                                                .iter_mut()
+                                                .enumerate()
+                                            {
+                                                let cursor = i + lines.0 == current_line;
+                                                if outdent {
+                                                    let removed = buffer.indent.outdent_line(line);
+                                                    if cursor {
+                                                        current_char =
+                                                            current_char.saturating_sub(removed);
+                                                    }
+                                                } else {
+                                                    let added = buffer.indent.indent_line(line);
+                                                    if cursor {
+                                                        current_char += added;
+                                                    }
+                                                }
+                                            }
+
+                                            // the whole range is undone at once
+                                            let count = lines.1 - lines.0 + 1;
+                                            if before != buffer.lines {
+                                                buffer
+                                                    .history
+                                                    .record(before, position.0, position.1, false);
+                                            }
+                                            info_text = match (outdent, count) {
+                                                (false, 1) => String::from("Indented 1 line"),
+                                                (false, _) => format!("Indented {} lines", count),
+                                                (true, 1) => String::from("Outdented 1 line"),
+                                                (true, _) => format!("Outdented {} lines", count),
+                                            };
+                                        }
+                                        Ok(CommandResult::Comment((from, to))) => {
+                                            let language = buffer.language;
+                                            match language.toggle_comments(&buffer.lines[from..=to])
+                                            {
+                                                Some(toggled) => {
+                                                    let before = buffer.lines.clone();
+                                                    let position = (current_line, current_char);
+                                                    for (i, (line, moved)) in
+                                                        toggled.into_iter().enumerate()
+                                                    {
+                                                        // the cursor stays on the same text
+                                                        if from + i == current_line {
+                                                            current_char = current_char
+                                                                .saturating_add_signed(moved);
+                                                        }
+                                                        buffer.lines[from + i] = line;
+                                                    }
+
+                                                    // the whole range is undone at once
+                                                    if before != buffer.lines {
+                                                        buffer.history.record(
+                                                            before, position.0, position.1, false,
+                                                        );
+                                                    }
+                                                }
+                                                None => {
+                                                    info_text = language.name.to_owned()
+                                                        + " has no comments"
+                                                }
+                                            }
+                                        }
+                                        Err(message) => info_text = message,
+                                    }
+                                    fit_cursor(&mut current_line, &mut current_char, &buffer.lines);
+                                    sync_hex_mode!();
+                                }
+                                ConsoleAction::NewFile(directory)
+                                | ConsoleAction::NewDirectory(directory) => {
+                                    let is_dir = matches!(action, ConsoleAction::NewDirectory(_));
+                                    info_text = match explorer.create(directory, &result, is_dir) {
+                                        Ok(_) => "Created '".to_owned() + result.trim() + "'",
+                                        Err(error) => {
+                                            "Could not create: ".to_owned()
+                                                + &describe_error(&error)
+                                        }
+                                    };
+                                }
+                                ConsoleAction::Rename(path) => {
+                                    match explorer.rename(path, &result) {
+                                        Ok(new_path) => {
+                                            // the open file, or a directory it is in
+                                            let open_file = PathBuf::from(&buffer.file_path);
+                                            if let Ok(rest) = open_file.strip_prefix(path) {
+                                                swap.remove(&buffer);
+                                                buffer.moved(new_path.join(rest));
+                                            }
+                                            info_text =
+                                                "Renamed to '".to_owned() + result.trim() + "'";
+                                        }
+                                        Err(error) => {
+                                            info_text = "Could not rename: ".to_owned()
+                                                + &describe_error(&error);
+                                        }
+                                    }
+                                }
                             }
                         }
+                        KeyCode::Esc if !console.is_searching() => {
+                            current_mode = console.get_return_mode()
+                        }
 
                         _ => console.handle_key_event(key_event),
                     }
+
+                    changed_line = ChangedLineType::All;
+                } else if let Event::Resize(width, height) = event {
+                    term_size = (width, height);
+                    changed_line = ChangedLineType::All;
+                    clear()?;
                 }
             }
+        } else if matches!(current_mode, Mode::PromptMode) {
+            if let Ok(Event::Key(key_event)) = read() {
+                if key_event.kind == KeyEventKind::Press {
+                    if let Some(answer) = prompt.handle_key_event(key_event) {
+                        bracket_cache = None;
+                        current_mode = prompt.get_return_mode();
+                        changed_line = ChangedLineType::All;
 
-            continue;
-        }
+                        match prompt.get_action().clone() {
+                            PromptAction::RecoverSwap => match answer {
+                                'r' => {
+                                    if let Some(bytes) = swap::find_leftover(&buffer) {
+                                        buffer.recover(bytes);
+                                        fit_cursor(
+                                            &mut current_line,
+                                            &mut current_char,
+                                            &buffer.lines,
+                                        );
+                                        info_text = String::from("Recovered unsaved changes");
+                                    }
+                                }
+                                'd' => swap::delete_leftover(&buffer),
+                                _ => {
+                                    info_text = match swap::keep_leftover(&buffer) {
+                                        Ok(kept) => {
+                                            "Kept the unsaved changes in '".to_owned()
+                                                + &kept
+                                                    .file_name()
+                                                    .unwrap_or_default()
+                                                    .to_string_lossy()
+                                                + "'"
+                                        }
+                                        Err(error) => {
+                                            "Could not keep the unsaved changes: ".to_owned()
+                                                + &describe_error(&error)
+                                        }
+                                    };
+                                }
+                            },
+                            PromptAction::DeleteEntry(path) => {
+                                if answer == 'd' {
+                                    // the open file or the directory it is in
+                                    let open = Path::new(&buffer.file_path).starts_with(&path);
+                                    info_text = match explorer.delete(&path) {
+                                        Ok(()) if open => {
+                                            buffer.forget_disk();
+                                            String::from("Deleted the open file, now unsaved")
+                                        }
+                                        Ok(()) => {
+                                            "Deleted '".to_owned()
+                                                + &path
+                                                    .file_name()
+                                                    .unwrap_or_default()
+                                                    .to_string_lossy()
+                                                + "'"
+                                        }
+                                        Err(error) => {
+                                            "Could not delete: ".to_owned()
+                                                + &describe_error(&error)
+                                        }
+                                    };
+                                }
+                            }
+                            PromptAction::ReplaceAll => {
+                                if let Some(mut replace) = replacing.take() {
+                                    if answer == 'r' {
+                                        record_replace!(replace);
+                                        replace.replace_rest(&mut buffer.lines);
+                                        fit_cursor(
+                                            &mut current_line,
+                                            &mut current_char,
+                                            &buffer.lines,
+                                        );
+                                        info_text = replace.summary();
+                                    }
+                                }
+                            }
+                            PromptAction::ReplaceMatch => {
+                                if let Some(replace) = &mut replacing {
+                                    if matches!(answer, 'y' | 'a') {
+                                        record_replace!(replace);
+                                    }
+
+                                    match answer {
+                                        'y' => {
+                                            if let Some((line, char)) =
+                                                replace.replace_current(&mut buffer.lines)
+                                            {
+                                                current_line = line;
+                                                current_char = char;
+                                            }
+                                        }
+                                        'n' => replace.skip_current(&buffer.lines),
+                                        'a' => {
+                                            replace.replace_rest(&mut buffer.lines);
+                                            fit_cursor(
+                                                &mut current_line,
+                                                &mut current_char,
+                                                &buffer.lines,
+                                            );
+                                        }
+                                        _ => replace.skip_current(&buffer.lines),
+                                    }
+
+                                    if matches!(answer, 'a' | 'q') {
+                                        info_text = replace.summary();
+                                        replacing = None;
+                                    }
+                                }
+                                replace_next!();
+                            }
+                            PromptAction::ExternalChange { saving, then } => match answer {
+                                'r' => {
+                                    swap.remove(&buffer);
+                                    match buffer.reload() {
+                                        Ok(()) => {
+                                            info_text = "Reloaded '".to_owned()
+                                                + &buffer.file_name
+                                                + "' from disk";
+                                            fit_cursor(
+                                                &mut current_line,
+                                                &mut current_char,
+                                                &buffer.lines,
+                                            );
+                                            sync_hex_mode!();
+                                        }
+                                        Err(error) => {
+                                            info_text = "Could not reload: ".to_owned()
+                                                + &describe_error(&error);
+                                        }
+                                    }
+                                }
+                                'd' => match buffer.disk_lines() {
+                                    Ok(disk_lines) => {
+                                        diff_view.open(
+                                            "Disk (-) → Buffer (+): ".to_owned()
+                                                + &buffer.file_name,
+                                            &disk_lines,
+                                            &buffer.lines,
+                                        );
+                                        current_mode = Mode::DiffMode;
+                                    }
+                                    Err(error) => {
+                                        info_text = "Could not read the file: ".to_owned()
+                                            + &describe_error(&error);
+                                    }
+                                },
+                                'k' => match buffer.keep_over_disk() {
+                                    Ok(()) if saving => {
+                                        if save_file!(buffer.save()) {
+                                            if let Some(action) = then {
+                                                run_confirmed!(*action);
+                                            }
+                                        }
+                                    }
+                                    Ok(()) => {
+                                        info_text = String::from(
+                                            "Kept your version, saving overwrites the file on disk",
+                                        );
+                                    }
+                                    Err(error) => {
+                                        info_text = "Could not read the file: ".to_owned()
+                                            + &describe_error(&error);
+                                    }
+                                },
+                                _ => info_text = String::from("Not saved"),
+                            },
+                            action => {
+                                let confirmed = match answer {
+                                    's' => save_buffer!(Some(action.clone())),
+                                    'd' => true,
+                                    _ => false,
+                                };
+
+                                if confirmed {
+                                    run_confirmed!(action);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        } else if matches!(current_mode, Mode::DiffMode) {
+            let editor_height = &calculate_editor_height(&(term_size.1 as usize));
+            match read() {
+                Ok(Event::Key(key_event)) if key_event.kind == KeyEventKind::Press => {
+                    // back to the question that opened the diff
+                    if diff_view.handle_key_event(key_event, editor_height) {
+                        current_mode = Mode::PromptMode;
+                    }
+                    changed_line = ChangedLineType::All;
+                }
+                Ok(Event::Mouse(mouse_event)) => {
+                    match mouse_event.kind {
+                        MouseEventKind::ScrollDown => {
+                            diff_view.scroll(settings.scroll_step as isize, editor_height)
+                        }
+                        MouseEventKind::ScrollUp => {
+                            diff_view.scroll(-(settings.scroll_step as isize), editor_height)
+                        }
+                        _ => {}
+                    }
+                    changed_line = ChangedLineType::All;
+                }
+                Ok(Event::Resize(width, height)) => {
+                    term_size = (width, height);
+                    changed_line = ChangedLineType::All;
+                    clear()?;
+                }
+                _ => {}
+            }
+        } else if matches!(current_mode, Mode::ResultsMode) {
+            match read() {
+                Ok(Event::Key(key_event)) if key_event.kind == KeyEventKind::Press => {
+                    let editor_height = &calculate_editor_height(&(term_size.1 as usize));
+                    if results.handle_key_event(key_event, editor_height) {
+                        current_mode = Mode::EditMode;
+
+                        let other_file = results
+                            .selected()
+                            .is_some_and(|hit| Path::new(&buffer.file_path) != hit.path);
+
+                        if key_event.code == KeyCode::Enter {
+                            if other_file && buffer.is_modified() {
+                                prompt.open_unsaved(
+                                    &buffer.file_name,
+                                    PromptAction::OpenResult,
+                                    current_mode,
+                                );
+                                current_mode = Mode::PromptMode;
+                            } else {
+                                open_result!();
+                            }
+                        }
+                    }
+                    changed_line = ChangedLineType::All;
+                }
+                Ok(Event::Resize(width, height)) => {
+                    term_size = (width, height);
+                    results.keep_visible(&calculate_editor_height(&(term_size.1 as usize)));
+                    changed_line = ChangedLineType::All;
+                    clear()?;
+                }
+                _ => {}
+            }
+        } else if matches!(current_mode, Mode::FinderMode) {
+            match read() {
+                Ok(Event::Key(key_event)) if key_event.kind == KeyEventKind::Press => {
+                    let editor_height = &calculate_editor_height(&(term_size.1 as usize));
+                    if finder.handle_key_event(key_event, editor_height) {
+                        current_mode = finder.get_return_mode();
+
+                        if key_event.code == KeyCode::Enter {
+                            if let Some(path) = finder.selected_path() {
+                                load_file!(path);
+                            }
+                        }
+                    }
+                    changed_line = ChangedLineType::All;
+                }
+                Ok(Event::Resize(width, height)) => {
+                    term_size = (width, height);
+                    finder.keep_visible(&calculate_editor_height(&(term_size.1 as usize)));
+                    changed_line = ChangedLineType::All;
+                    clear()?;
+                }
+                _ => {}
+            }
+        } else if matches!(current_mode, Mode::MenuMode) {
+            let menu_action = match read() {
+                Ok(Event::Key(key_event)) if key_event.kind == KeyEventKind::Press => {
+                    menu.handle_key_event(key_event)
+                }
+                Ok(Event::Mouse(mouse_event)) => match mouse_event.kind {
+                    MouseEventKind::Down(MouseButton::Left) => menu.click(
+                        mouse_event.column as usize,
+                        mouse_event.row as usize,
+                        &keymap,
+                    ),
+                    _ => MenuAction::None,
+                },
+                Ok(Event::Resize(width, height)) => {
+                    term_size = (width, height);
+                    clear()?;
+                    MenuAction::None
+                }
+                _ => MenuAction::None,
+            };
+
+            match menu_action {
+                MenuAction::None => {}
+                MenuAction::Close => current_mode = menu.get_return_mode(),
+                MenuAction::Run(action) => {
+                    current_mode = menu.get_return_mode();
+                    run_action!(action);
+                }
+            }
+            changed_line = ChangedLineType::All;
+        } else if matches!(current_mode, Mode::PaletteMode) {
+            if let Ok(Event::Key(key_event)) = read() {
+                if key_event.kind == KeyEventKind::Press {
+                    let editor_height = &calculate_editor_height(&(term_size.1 as usize));
+                    if palette.handle_key_event(key_event, editor_height) {
+                        current_mode = palette.get_return_mode();
+
+                        if key_event.code == KeyCode::Enter {
+                            if let Some(action) = palette.selected_action() {
+                                run_action!(action);
+                            }
+                        }
+                    }
+                    changed_line = ChangedLineType::All;
+                }
+            }
+        } else if matches!(current_mode, Mode::ExplorerMode) {
+            let editor_height = &calculate_editor_height(&(term_size.1 as usize));
+            match read() {
+                Ok(Event::Key(key_event)) if key_event.kind == KeyEventKind::Press => {
+                    match explorer.handle_key_event(key_event, editor_height) {
+                        ExplorerAction::None => {}
+                        ExplorerAction::Leave => current_mode = explorer.get_return_mode(),
+                        ExplorerAction::Hide => {
+                            explorer.visible = false;
+                            current_mode = explorer.get_return_mode();
+                        }
+                        ExplorerAction::Reveal => {
+                            explorer.reveal(Path::new(&buffer.file_path), editor_height);
+                        }
+                        ExplorerAction::Open(path) => {
+                            current_mode = explorer.get_return_mode();
+
+                            if Path::new(&buffer.file_path) != path {
+                                if buffer.is_modified() {
+                                    prompt.open_unsaved(
+                                        &buffer.file_name,
+                                        PromptAction::OpenEntry(path),
+                                        current_mode,
+                                    );
+                                    current_mode = Mode::PromptMode;
+                                } else {
+                                    load_file!(path);
+                                }
+                            }
+                        }
+                        ExplorerAction::NewFile(directory) => {
+                            console.open_with(ConsoleAction::NewFile(directory), "New file: ");
+                            current_mode = Mode::ConsoleMode;
+                        }
+                        ExplorerAction::NewDirectory(directory) => {
+                            console.open_with(
+                                ConsoleAction::NewDirectory(directory),
+                                "New directory: ",
+                            );
+                            current_mode = Mode::ConsoleMode;
+                        }
+                        ExplorerAction::Rename(path) => {
+                            let name = path.file_name().unwrap_or_default().to_string_lossy();
+                            let label = "Rename '".to_owned() + &name + "' to: ";
+                            console.open_with(ConsoleAction::Rename(path.clone()), &label);
+                            current_mode = Mode::ConsoleMode;
+                        }
+                        ExplorerAction::Delete(path) => {
+                            let name = path.file_name().unwrap_or_default().to_string_lossy();
+                            let question = match path.is_dir() {
+                                true => "Delete '".to_owned() + &name + "' and everything in it?",
+                                false => "Delete '".to_owned() + &name + "'?",
+                            };
+                            prompt.open(
+                                question,
+                                vec![('d', "Delete"), ('c', "Cancel")],
+                                PromptAction::DeleteEntry(path.clone()),
+                                Mode::ExplorerMode,
+                            );
+                            current_mode = Mode::PromptMode;
+                        }
+                    }
+                    changed_line = ChangedLineType::All;
+                }
+                Ok(Event::Mouse(mouse_event)) => {
+                    if let MouseEventKind::Down(MouseButton::Left) = mouse_event.kind {
+                        let term_width = term_size.0 as usize;
+                        let editor_width = term_width - explorer.width(term_width);
+
+                        // a click into the editor gives the focus back
+                        if (mouse_event.column as usize) < editor_width {
+                            current_mode = explorer.get_return_mode();
+                        } else if mouse_event.row >= 3 {
+                            explorer.click(mouse_event.row as usize - 3, editor_height);
+                        }
+                        changed_line = ChangedLineType::All;
+                    }
+                }
+                Ok(Event::Resize(width, height)) => {
+                    term_size = (width, height);
+                    changed_line = ChangedLineType::All;
+                    clear()?;
+                }
+                _ => {}
+            }
+        } else if matches!(current_mode, Mode::FindMode) {
+            if let Ok(Event::Key(key_event)) = read() {
+                if key_event.kind == KeyEventKind::Press {
+                    let closed = search.handle_key_event(
+                        key_event,
+                        &buffer.lines,
+                        &mut current_line,
+                        &mut current_char,
+                    );
+                    if closed {
+                        current_mode = search.get_return_mode();
+                        if key_event.code == KeyCode::Enter {
+                            info_text = search.describe();
+                        }
+                    }
 
-        if let Ok(event) = read() {
+                    let editor_height = &calculate_editor_height(&(term_size.1 as usize));
+                    jump_to_editor_point(&mut current_line, &mut current_scroll, editor_height);
+                    changed_line = ChangedLineType::All;
+                }
+            }
+        } else if let Ok(event) = read() {
+            if let Event::FocusLost = event {
+                if settings.save_on_unfocus && buffer.is_modified() {
+                    save_buffer!(None);
+                    changed_line = ChangedLineType::All;
+                }
+            }
+            if let Event::FocusGained = event {
+                if buffer.changed_on_disk() {
+                    prompt.open_external_change(
+                        &buffer.file_name,
+                        buffer.hex.is_none(),
+                        false,
+                        None,
+                        current_mode,
+                    );
+                    current_mode = Mode::PromptMode;
+                    changed_line = ChangedLineType::All;
+                }
+            }
             if let Event::Resize(width, height) = event {
                 term_size.0 = width;
                 term_size.1 = height;
                 changed_line = ChangedLineType::All;
                 clear()?;
             }
-            if let Event::Mouse(mouse_event) = event {
+            if let (Event::Mouse(mouse_event), Some(hex)) = (&event, &mut buffer.hex) {
+                let editor_height = &calculate_editor_height(&(term_size.1 as usize));
+                match mouse_event.kind {
+                    MouseEventKind::ScrollDown => {
+                        hex.scroll(settings.scroll_step as isize, editor_height)
+                    }
+                    MouseEventKind::ScrollUp => {
+                        hex.scroll(-(settings.scroll_step as isize), editor_height)
+                    }
+                    _ => {}
+                }
+                changed_line = ChangedLineType::All;
+            } else if let Event::Mouse(mouse_event) = event {
                 match mouse_event.kind {
                     MouseEventKind::ScrollDown => {
                         if current_scroll + calculate_editor_height(&(term_size.1 as usize))
-                            < lines.len()
+                            < buffer.lines.len()
                         {
-                            current_scroll += 2;
+                            current_scroll += settings.scroll_step;
                             changed_line = ChangedLineType::All;
                             clear()?;
                         }
@@ -149,7 +1360,7 @@ fn main() -> io::Result<()> {
                         if current_scroll == 0 {
                             changed_line = ChangedLineType::None;
                         } else {
-                            current_scroll = std::cmp::max(0, current_scroll as i32 - 2) as usize;
+                            current_scroll = current_scroll.saturating_sub(settings.scroll_step);
                             changed_line = ChangedLineType::All;
                             clear()?;
                         }
@@ -160,15 +1371,44 @@ fn main() -> io::Result<()> {
                     //     changed_line = true;
                     //     clear()?;
                     // }
+                    // a title in the header opens its menu
+                    MouseEventKind::Down(MouseButton::Left)
+                        if mouse_event.row == 0
+                            && matches!(
+                                current_mode,
+                                Mode::WriteMode | Mode::EditMode | Mode::HexMode
+                            ) =>
+                    {
+                        if let Some(i) = menu.title_at(mouse_event.column as usize) {
+                            menu.open(i, current_mode);
+                            current_mode = Mode::MenuMode;
+                            changed_line = ChangedLineType::All;
+                        }
+                    }
+                    // a row of the explorer, selects it and focuses the tree
+                    MouseEventKind::Down(MouseButton::Left)
+                        if (mouse_event.column as usize)
+                            >= term_size.0 as usize - explorer.width(term_size.0 as usize) =>
+                    {
+                        let editor_height = &calculate_editor_height(&(term_size.1 as usize));
+                        if mouse_event.row >= 3
+                            && explorer.click(mouse_event.row as usize - 3, editor_height)
+                        {
+                            explorer.focus(env::current_dir().unwrap(), current_mode);
+                            current_mode = Mode::ExplorerMode;
+                            changed_line = ChangedLineType::All;
+                        }
+                    }
                     MouseEventKind::Down(MouseButton::Left) => {
                         // lines[0] = String::from("click x: ") + &mouse_event.column.to_string();
                         // lines[1] = String::from("click y: ") + &mouse_event.row.to_string();
 
-                        if file_name == "" {
+                        if buffer.file_name == "" {
                             continue;
                         }
 
-                        if mouse_event.column < 7 {
+                        let gutter = settings.gutter_width() as u16;
+                        if mouse_event.column < gutter {
                             continue;
                         }
 
@@ -177,15 +1417,18 @@ fn main() -> io::Result<()> {
                         }
 
                         current_line = mouse_event.row as usize + current_scroll - 3;
-                        current_char = mouse_event.column as usize - 7;
+                        let column = (mouse_event.column - gutter) as usize;
 
-                        if current_line > lines.len() {
-                            current_line = lines.len();
+                        if current_line >= buffer.lines.len() {
+                            current_line = buffer.lines.len() - 1;
                         }
 
-                        if current_char > lines[current_line].len() {
-                            current_char = lines[current_line].len();
-                        }
+                        // columns and chars differ once there are tabs
+                        current_char = char_at_column(
+                            &buffer.lines[current_line],
+                            column,
+                            buffer.indent.width,
+                        );
 
                         changed_line = ChangedLineType::All;
                     }
@@ -197,73 +1440,52 @@ fn main() -> io::Result<()> {
                     continue;
                 }
 
-                let mut block_event = false;
-
-                match key_event.code {
-                    KeyCode::Esc => {
-                        clear()?;
-                        for_real_clear()?;
-                        move_to(0, 0)?;
-                        break;
-                    }
-                    KeyCode::F(2) => {}
-                    KeyCode::Char('j') => {
-                        if key_event.modifiers == KeyModifiers::ALT {
-                            match current_mode {
-                                Mode::WriteMode => current_mode = Mode::EditMode,
-                                Mode::EditMode => {
-                                    move_left(&mut current_char, &current_line, &mut lines, true)?;
-                                    ()
-                                }
-                                _ => {}
-                            }
-                        }
-                    }
-                    KeyCode::Char('s') => {
-                        if key_event.modifiers == KeyModifiers::CONTROL {
-                            info_text = save_file_as(&lines, &file_name)?;
-                            block_event = true;
-                            changed_line = ChangedLineType::All;
-                        }
-                    }
-                    KeyCode::Char('n') => {
-                        if key_event.modifiers == KeyModifiers::CONTROL {
-                            block_event = true;
-                            file_name = String::from("new_file.txt");
-                            lines = vec![String::from("")];
-                            current_line = 0;
-                            current_char = 0;
-                            current_scroll = 0;
-                            changed_line = ChangedLineType::All;
-                        }
-                    }
-                    KeyCode::Char('o') => {
-                        if key_event.modifiers == KeyModifiers::CONTROL {
-                            block_event = true;
-                            let file = rfd::FileDialog::new().set_directory(&file_path).pick_file();
-
-                            file_path = file.unwrap().into_os_string().into_string().unwrap();
-                            file_name = file_path.split(r"\").last().unwrap().to_string();
-
-                            let file = read_to_string(&file_path)?;
-                            lines = file.lines().map(|s| s.to_string()).collect();
-                            current_line = 0;
-                            current_char = 0;
-                            current_scroll = 0;
-
-                            changed_line = ChangedLineType::All;
-                        }
-                    }
-                    _ => {}
+                let was_pending = keymap.describe_pending().is_some();
+                let press = keymap.press(key_event, current_mode);
+                // the first keys of a sequence that didn't finish are handled
+                // as if they weren't bound, before the key that ended it
+                let mut keys = keymap.take_unmatched();
+                if let KeyPress::None = press {
+                    keys.push(key_event);
                 }
 
-                if key_event.code == KeyCode::Esc {}
-
-                if !block_event {
+                for key_event in keys {
                     let editor_height = &calculate_editor_height(&(term_size.1 as usize));
+                    // only keys that edit the text save the lines for undo, and
+                    // typing on in the same run doesn't need them again
+                    let edits = matches!(current_mode, Mode::WriteMode)
+                        && matches!(
+                            key_event.code,
+                            KeyCode::Char(_)
+                                | KeyCode::Enter
+                                | KeyCode::Tab
+                                | KeyCode::BackTab
+                                | KeyCode::Backspace
+                        );
+                    let typing = edits && matches!(key_event.code, KeyCode::Char(_));
+                    let position = (current_line, current_char);
+                    let before = match typing && buffer.history.continues_typing(position) {
+                        true => None,
+                        false => edits.then(|| buffer.lines.clone()),
+                    };
+
                     match current_mode {
-                        Mode::ConsoleMode => {}
-                        Mode::MenuMode => changed_line = menu.handle_key_event(key_event)?,
+                        Mode::ConsoleMode
+                        | Mode::PromptMode
+                        | Mode::DiffMode
+                        | Mode::FindMode
+                        | Mode::ResultsMode
+                        | Mode::FinderMode
+                        | Mode::ExplorerMode
+                        | Mode::PaletteMode
+                        | Mode::MenuMode
+                        // keys without an action do nothing in edit mode
+                        | Mode::EditMode => {}
+                        Mode::HexMode => {
+                            if let Some(hex) = &mut buffer.hex {
+                                changed_line = hex.handle_key_event(key_event, editor_height);
+                            }
+                        }
                         Mode::WriteMode => {
                             changed_line = writemode::handle_key_event(
                                 key_event,
@@ -272,28 +1494,57 @@ fn main() -> io::Result<()> {
                                 &mut current_char,
                                 &mut current_scroll,
                                 editor_height,
-                                &mut lines,
+                                &mut buffer.lines,
+                                buffer.indent,
+                                buffer.language,
+                                settings.auto_close,
+                                &mut auto_closed,
+                                &mut bracket_cache,
                                 initial,
                             )?;
                         }
-                        Mode::EditMode => {
-                            changed_line = editmode::handle_key_event(
-                                key_event,
-                                &mut current_line,
-                                &mut current_char,
-                                &mut current_scroll,
-                                editor_height,
-                                &mut current_mode,
-                                &mut lines,
-                            )?
-                        }
                     }
+                    // the brackets are scanned again after any edit
+                    if edits {
+                        bracket_cache = None;
+                    }
+
+                    match before {
+                        Some(before) if before != buffer.lines => buffer
+                            .history
+                            .record(before, position.0, position.1, typing),
+                        None if typing => buffer.history.typed(position),
+                        _ => {}
+                    }
+                }
+
+                if let KeyPress::Action(action) = press {
+                    auto_closed.clear();
+                    changed_line = ChangedLineType::All;
+                    run_action!(action);
+                }
+
+                // the status bar shows the start of a sequence while it lasts
+                if was_pending != keymap.describe_pending().is_some() {
+                    changed_line = match changed_line {
+                        ChangedLineType::None => ChangedLineType::Skeleton,
+                        _ => ChangedLineType::All,
+                    };
                 }
 
                 // initial = false;
             }
         }
 
+        if !idle {
+            last_input = Instant::now();
+        }
+        if current_mode != mode_before {
+            keymap.reset();
+            auto_closed.clear();
+        }
+        buffer.history.cursor_at((current_line, current_char));
+
         move_to(0, 0)?;
         // purge()?;
 
@@ -306,16 +1557,34 @@ fn main() -> io::Result<()> {
             initial = false;
         }
 
+        // the bracket belonging to the one under the cursor, or the one just
+        // typed in front of it
+        let brackets =
+            bracket_cache.get_or_insert_with(|| Brackets::scan(&buffer.lines, buffer.language));
+        let matched_bracket = brackets
+            .partner(current_line, current_char)
+            .or_else(|| brackets.partner(current_line, current_char.checked_sub(1)?));
+        let bracket_warning = match brackets
+            .unbalanced()
+            .filter(|_| !buffer.language.is_plain_text())
+        {
+            Some((line, _, c)) => format!("Unbalanced {} on line {}", c, line + 1),
+            None => String::new(),
+        };
+
         macro_rules! draw_skeleton {
             () => {
                 skeleton::draw_skeleton(
                     &(term_size.0 as usize),
                     &(term_size.1 as usize),
+                    &buffer,
                     &info_text,
                     &current_mode,
                     &current_line,
                     &current_char,
                     &current_scroll,
+                    &bracket_warning,
+                    &keymap,
                 )?;
             };
         }
@@ -323,11 +1592,31 @@ fn main() -> io::Result<()> {
         macro_rules! draw_menu {
             () => {
                 match current_mode {
-                    Mode::MenuMode => menu.draw()?,
+                    Mode::MenuMode => menu.draw(&keymap)?,
                     Mode::ConsoleMode => {
-                        move_to(0, &term_size.1 - 3)?;
                         menu.draw_header()?;
-                        console.draw();
+                        move_to(0, term_size.1 - 1)?;
+                        console.draw(&(term_size.0 as usize));
+                    }
+                    Mode::PromptMode => {
+                        menu.draw_header()?;
+                        move_to(0, term_size.1 - 1)?;
+                        prompt.draw(&(term_size.0 as usize));
+                    }
+                    Mode::FindMode => {
+                        menu.draw_header()?;
+                        move_to(0, term_size.1 - 1)?;
+                        search.draw(&(term_size.0 as usize));
+                    }
+                    Mode::FinderMode => {
+                        menu.draw_header()?;
+                        move_to(0, term_size.1 - 1)?;
+                        finder.draw_input(&(term_size.0 as usize));
+                    }
+                    Mode::PaletteMode => {
+                        menu.draw_header()?;
+                        move_to(0, term_size.1 - 1)?;
+                        palette.draw_input(&(term_size.0 as usize));
                     }
                     _ => {
                         menu.draw_header()?;
@@ -336,23 +1625,71 @@ fn main() -> io::Result<()> {
             };
         }
 
+        // search matches and the match a replace asks about
+        let highlights = match (current_mode, &replacing) {
+            (Mode::FindMode, _) => search.highlights(),
+            (Mode::PromptMode, Some(replace)) => replace
+                .highlight(&buffer.lines)
+                .map(|(line, start, end)| (line, start, end, true))
+                .into_iter()
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        // the explorer takes its columns from the right of the editor
+        let editor_width = term_size.0 as usize - explorer.width(term_size.0 as usize);
+
         match changed_line {
             ChangedLineType::All => {
                 move_to(0, 0)?;
                 draw_skeleton!();
                 move_to(0, 0)?;
-                draw_editor(
-                    &lines,
-                    &mut cached_lines,
-                    &current_mode,
-                    &(term_size.1 as usize),
-                    &(term_size.0 as usize),
-                    &current_line,
-                    &current_char,
-                    &current_scroll,
-                    &info_text,
-                    &file_name,
-                );
+                if matches!(current_mode, Mode::DiffMode) {
+                    diff_view.draw(
+                        &editor_width,
+                        &calculate_editor_height(&(term_size.1 as usize)),
+                    );
+                } else if matches!(current_mode, Mode::ResultsMode) {
+                    results.draw(
+                        &editor_width,
+                        &calculate_editor_height(&(term_size.1 as usize)),
+                    );
+                } else if matches!(current_mode, Mode::FinderMode) {
+                    finder.draw(
+                        &editor_width,
+                        &calculate_editor_height(&(term_size.1 as usize)),
+                    );
+                } else if matches!(current_mode, Mode::PaletteMode) {
+                    palette.draw(
+                        &editor_width,
+                        &calculate_editor_height(&(term_size.1 as usize)),
+                        &keymap,
+                    );
+                } else {
+                    draw_editor(
+                        &buffer,
+                        &mut cached_lines,
+                        &current_mode,
+                        &(term_size.1 as usize),
+                        &editor_width,
+                        &current_line,
+                        &current_char,
+                        &current_scroll,
+                        &info_text,
+                        &highlights,
+                        matched_bracket,
+                        &settings.line_number_width,
+                    );
+                }
+                if explorer.visible {
+                    explorer.draw(
+                        editor_width,
+                        &(term_size.0 as usize),
+                        &(term_size.1 as usize),
+                        matches!(current_mode, Mode::ExplorerMode),
+                        Path::new(&buffer.file_path),
+                    );
+                }
                 move_to(0, 0)?;
                 draw_menu!();
             }
@@ -363,32 +1700,46 @@ fn main() -> io::Result<()> {
             ChangedLineType::Line(line) => {
                 move_to(0, current_line as u16 + 3)?;
                 let editor_height = &(term_size.1 as usize);
-                let editor_width = &(term_size.0 as usize);
                 jump_to_editor_point(&mut current_line, &mut current_scroll, editor_height);
                 draw_single_line(
                     &current_line,
                     &current_char,
-                    &lines,
+                    &buffer,
                     &mut cached_lines,
-                    generate_select_char(&current_char, &current_line, &lines, &current_mode),
+                    generate_select_char(
+                        &current_char,
+                        &current_line,
+                        &buffer.lines,
+                        &current_mode,
+                    ),
                     line as usize,
-                    editor_width,
+                    &[],
+                    None,
+                    &editor_width,
+                    &settings.line_number_width,
                 );
             }
             ChangedLineType::Lines(i, j) => {
                 for line in i..j + 1 {
                     move_to(0, line as u16 + 3)?;
                     let editor_height = &(term_size.1 as usize);
-                    let editor_width = &(term_size.0 as usize);
                     jump_to_editor_point(&mut current_line, &mut current_scroll, editor_height);
                     draw_single_line(
                         &current_line,
                         &current_char,
-                        &lines,
+                        &buffer,
                         &cached_lines,
-                        generate_select_char(&current_char, &current_line, &lines, &current_mode),
+                        generate_select_char(
+                            &current_char,
+                            &current_line,
+                            &buffer.lines,
+                            &current_mode,
+                        ),
                         line,
-                        editor_width,
+                        &[],
+                        None,
+                        &editor_width,
+                        &settings.line_number_width,
                     )
                 }
             }
@@ -396,16 +1747,18 @@ fn main() -> io::Result<()> {
                 let editor_height = &(term_size.1 as usize);
                 jump_to_editor_point(&mut current_line, &mut current_scroll, editor_height);
                 draw_editor(
-                    &lines,
+                    &buffer,
                     &mut cached_lines,
                     &current_mode,
                     editor_height,
-                    &(term_size.0 as usize),
+                    &editor_width,
                     &current_line,
                     &current_char,
                     &current_scroll,
                     &info_text,
-                    &file_name,
+                    &highlights,
+                    matched_bracket,
+                    &settings.line_number_width,
                 )
             }
             _ => {}
@@ -415,6 +1768,7 @@ fn main() -> io::Result<()> {
     }
 
     disable_raw_mode()?;
+    execute!(io::stdout(), DisableMouseCapture, DisableFocusChange)?;
     execute!(io::stdout(), Show)?;
     Ok(())
 }
@@ -422,12 +1776,16 @@ fn main() -> io::Result<()> {
 fn draw_single_line(
     current_line: &usize,
     current_char: &usize,
-    lines: &Vec<String>,
+    buffer: &Buffer,
     cached_lines: &Vec<Vec<StyledContent<String>>>,
     char: StyledContent<char>,
     i: usize,
+    highlights: &[(usize, usize, bool)],
+    bracket: Option<usize>,
     width: &usize,
+    line_number_width: &usize,
 ) {
+    let lines = &buffer.lines;
     let line;
     let written_line;
     if i < lines.len() {
@@ -438,31 +1796,38 @@ fn draw_single_line(
         written_line = false;
     }
 
-    // strings before and after cursor (char variable)
-    let mut start = String::new();
-    let mut end = String::new();
+    // tabs are drawn as spaces, each cell remembers the char it belongs to
+    let cells = expand_tabs(&line, buffer.indent.width);
 
-    let line_chars: Vec<char> = line.chars().collect();
+    // cells before and after cursor (char variable)
+    let mut start: &[(char, usize)] = &cells;
+    let mut end: &[(char, usize)] = &[];
 
     if *current_line == i as usize {
-        start = line_chars[0..*current_char].iter().collect();
-        if current_char < &line.len() {
-            end = line_chars[current_char + 1..line_chars.len()]
-                .iter()
-                .collect::<String>();
-        }
-    } else {
-        start = line.clone();
+        let cursor = cells
+            .iter()
+            .position(|(_, index)| index == current_char)
+            .unwrap_or(cells.len());
+        start = &cells[..cursor];
+        end = cells.get(cursor + 1..).unwrap_or(&[]);
     }
 
+    // text past the editor width is cut off, so it doesn't run into the explorer
+    let space = width.saturating_sub(line_number_width + 4);
+    start = &start[..start.len().min(space)];
+    end = &end[..end.len().min(space - start.len())];
+
     let mut line_indicator = String::new();
     let mut divider = " │ ";
 
     if written_line {
-        line_indicator.push_str(&str::repeat(" ", 4 - (i + 1).to_string().len()));
+        line_indicator.push_str(&str::repeat(
+            " ",
+            line_number_width.saturating_sub((i + 1).to_string().len()),
+        ));
         line_indicator.push_str(&(i + 1).to_string());
     } else {
-        line_indicator.push_str("    ");
+        line_indicator.push_str(&str::repeat(" ", *line_number_width));
         divider = "   "
     }
 
@@ -478,13 +1843,17 @@ fn draw_single_line(
         //     print!("{}", styled_on_secondary(value));
         // }
 
-        print!("{}", on_secondary(&start));
+        print_highlighted(start, highlights, bracket);
 
         if *current_line == i {
-            print!("{}", char);
+            // the cursor takes the first column of a tab
+            match char.content() {
+                '\t' => print!("{}", StyledContent::new(*char.style(), ' ')),
+                _ => print!("{}", char),
+            }
         }
 
-        print!("{}", on_secondary(&end));
+        print_highlighted(end, highlights, bracket);
         // for value in format(&end) {
         //     print!("{}", styled_on_secondary(value));
         // }
@@ -492,7 +1861,7 @@ fn draw_single_line(
 
     print!(
         "{}",
-        on_secondary(&str::repeat(" ", width - 8 - start.len() - end.len()))
+        on_secondary(&str::repeat(" ", space - start.len() - end.len()))
     );
 
     if *current_line != i {
@@ -500,13 +1869,46 @@ fn draw_single_line(
     }
 }
 
+// prints drawn cells of a line, search matches and the bracket matching the
+// one at the cursor get marked
+fn print_highlighted(
+    cells: &[(char, usize)],
+    highlights: &[(usize, usize, bool)],
+    bracket: Option<usize>,
+) {
+    let highlight_at = |cell: usize| {
+        let found = highlights
+            .iter()
+            .find(|(start, end, _)| (*start..*end).contains(&cells[cell].1))
+            .map(|(_, _, selected)| *selected);
+        (found, bracket == Some(cells[cell].1))
+    };
+
+    let mut run_start = 0;
+
+    for i in 1..=cells.len() {
+        if i < cells.len() && highlight_at(i) == highlight_at(run_start) {
+            continue;
+        }
+
+        let run: String = cells[run_start..i].iter().map(|(c, _)| c).collect();
+        match highlight_at(run_start) {
+            (Some(true), _) => print!("{}", run.black().on_yellow()),
+            (Some(false), _) => print!("{}", run.black().on_dark_yellow()),
+            (None, true) => print!("{}", run.black().on_grey()),
+            (None, false) => print!("{}", on_secondary(&run)),
+        }
+        run_start = i;
+    }
+}
+
 fn generate_select_char(
     current_char: &usize,
     current_line: &usize,
     lines: &Vec<String>,
     mode: &Mode,
 ) -> StyledContent<char> {
-    let mut select_char = match *current_char == lines[*current_line].len() {
+    let mut select_char = match *current_char == char_count(&lines[*current_line]) {
         true => ' '.on_white().slow_blink(),
         false => lines[*current_line]
             .chars()
@@ -531,7 +1933,7 @@ fn format_all_lines(lines: &Vec<String>, cached_lines: &mut Vec<Vec<StyledConten
 }
 
 fn draw_editor(
-    lines: &Vec<String>,
+    buffer: &Buffer,
     cached_lines: &mut Vec<Vec<StyledContent<String>>>,
     mode: &Mode,
     height: &usize,
@@ -540,37 +1942,59 @@ fn draw_editor(
     current_char: &usize,
     current_scroll: &usize,
     info_text: &String,
-    file_name: &String,
+    highlights: &[(usize, usize, usize, bool)],
+    matched_bracket: Option<(usize, usize)>,
+    line_number_width: &usize,
 ) {
+    let lines = &buffer.lines;
     let select_char = generate_select_char(current_char, current_line, lines, mode);
 
     // print!("\n");
 
     print!("\n");
-    if file_name == "" {
+    if buffer.file_name == "" {
         draw_help_window(width, height);
         return;
     }
 
     print!("{}", on_secondary(" "));
-    print!("{}", on_secondary(file_name));
+    print!("{}", on_secondary(&buffer.file_name));
+    if buffer.is_modified() {
+        print!("{}", on_secondary(" ●").yellow());
+    }
     print!("{}", on_secondary("  "));
     print!("\n");
     print!("{}\n", on_secondary(&str::repeat(" ", *width)));
 
     let editor_height = calculate_editor_height(height);
 
+    if let Some(hex) = &buffer.hex {
+        hex.draw(width, &editor_height);
+        return;
+    }
+
     for i in *current_scroll..editor_height + current_scroll {
         // print!("        ");
 
+        let line_highlights: Vec<(usize, usize, bool)> = highlights
+            .iter()
+            .filter(|(line, _, _, _)| *line == i)
+            .map(|(_, start, end, selected)| (*start, *end, *selected))
+            .collect();
+
         draw_single_line(
             current_line,
             current_char,
-            lines,
+            buffer,
             cached_lines,
             select_char,
             i,
+            &line_highlights,
+            matched_bracket
+                .filter(|(line, _)| *line == i)
+                .map(|(_, c)| c),
             width,
+            line_number_width,
         );
 
         println!("");
@@ -612,3 +2036,11 @@ fn draw_in_center(width: &usize, height: &usize, text: Vec<&str>) {
         i += 1;
     }
 }
+
+// the first problem in the config files, the others are only counted
+fn describe_config_errors(errors: &[String]) -> String {
+    match errors.len() {
+        1 => errors[0].clone(),
+        n => format!("{} (and {} more)", errors[0], n - 1),
+    }
+}
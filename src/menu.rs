@@ -2,34 +2,86 @@ use std::io;
 
 use crossterm::{
     cursor::MoveTo,
-    event::{KeyCode, KeyEvent},
+    event::{KeyCode, KeyEvent, KeyModifiers},
     execute,
-    style::{Color, Stylize},
+    style::Stylize,
 };
 
-use crate::{on_main, ChangedLineType};
+use crate::{actions::Action, functions::char_count, keymap::Keymap, on_main, on_secondary, Mode};
+
+// what the editor should do after a key or click in the menu
+pub enum MenuAction {
+    None,
+    Close,
+    Run(Action),
+}
+
+// the header is "Pico-Term  │  " followed by the titles
+const TITLES_START: usize = 14;
+const TITLE_GAP: usize = 2;
 
 pub struct Menu<'a> {
     menu_item: usize,
     menu_option: usize,
-    titles: [&'a str; 3],
-    items: [Vec<&'a str>; 3],
+    titles: [&'a str; 4],
+    items: [Vec<(&'a str, Action)>; 4],
+    return_mode: Mode,
 }
 
 impl<'a> Menu<'a> {
     pub fn new() -> Self {
         return Self {
             menu_item: 0,
-            menu_option: 99,
-            titles: ["File", "Color", "Settings"],
+            menu_option: 0,
+            titles: ["File", "Edit", "Color", "Settings"],
             items: [
-                vec!["New file", "Open file", "Save", "Save as"],
-                vec!["Theme1", "Theme2", "Theme3"],
-                vec!["Save on unfocus", "Something"],
+                vec![
+                    ("New file", Action::NewFile),
+                    ("Open file", Action::OpenFile),
+                    ("Save", Action::Save),
+                    ("Save as...", Action::SaveAs),
+                    ("File explorer", Action::ToggleExplorer),
+                    ("Quit", Action::Quit),
+                ],
+                vec![
+                    ("Undo", Action::Undo),
+                    ("Redo", Action::Redo),
+                    ("Find", Action::Find),
+                    ("Replace...", Action::Replace),
+                    ("Search in files...", Action::SearchFiles),
+                    ("Go to line...", Action::GotoLine),
+                    ("Indent line", Action::IndentLine),
+                    ("Outdent line", Action::OutdentLine),
+                    ("Matching bracket", Action::MatchBracket),
+                    ("Toggle comment", Action::ToggleComment),
+                    ("Command palette", Action::CommandPalette),
+                ],
+                vec![
+                    ("Pico", Action::Theme(0)),
+                    ("Midnight", Action::Theme(1)),
+                    ("Forest", Action::Theme(2)),
+                ],
+                vec![
+                    ("Save on unfocus", Action::ToggleSaveOnUnfocus),
+                    ("Hex mode", Action::ToggleHex),
+                    ("Language...", Action::SetLanguage),
+                    ("Options...", Action::SetOption),
+                ],
             ],
+            return_mode: Mode::WriteMode,
         };
     }
 
+    pub fn open(&mut self, menu_option: usize, return_mode: Mode) {
+        self.menu_option = menu_option.min(self.titles.len() - 1);
+        self.menu_item = 0;
+        self.return_mode = return_mode;
+    }
+
+    pub fn get_return_mode(&self) -> Mode {
+        self.return_mode
+    }
+
     fn adjust_item_pos(&mut self) {
         self.menu_item = 0;
     }
@@ -61,94 +113,140 @@ impl<'a> Menu<'a> {
         self.menu_item = (self.menu_item + 1) % self.items[self.menu_option].len();
     }
 
-    pub fn reset(&mut self) {
-        self.menu_option = 0;
-        self.menu_item = 0;
+    // the column a title starts at
+    fn title_pos(&self, i: usize) -> usize {
+        TITLES_START
+            + self.titles[..i]
+                .iter()
+                .map(|title| title.len() + TITLE_GAP)
+                .sum::<usize>()
     }
 
-    pub fn hide(&mut self) {
-        self.menu_option = 99;
+    // the menu whose title is at a column of the header
+    pub fn title_at(&self, column: usize) -> Option<usize> {
+        (0..self.titles.len()).find(|i| {
+            let start = self.title_pos(*i);
+            column >= start && column < start + self.titles[*i].len()
+        })
     }
 
-    pub fn draw_header(&mut self) -> io::Result<usize> {
-        let mut start_pos = 0;
+    // the menu an ALT shortcut opens, its title's first letter
+    pub fn title_for_key(&self, key_event: KeyEvent) -> Option<usize> {
+        let KeyCode::Char(c) = key_event.code else {
+            return None;
+        };
+        if !key_event.modifiers.contains(KeyModifiers::ALT) {
+            return None;
+        }
 
-        print!("{}", on_main("Pico-Term").blue());
-        print!("{}", on_main("  │  File  Edit  Settings").dark_grey());
+        self.titles
+            .iter()
+            .position(|title| title.to_lowercase().starts_with(c.to_ascii_lowercase()))
+    }
 
-        start_pos += 11;
+    pub fn draw_header(&mut self) -> io::Result<usize> {
+        self.draw_titles(false)
+    }
 
-        return Ok(1);
+    // returns the column the open menu's items start at
+    fn draw_titles(&self, open: bool) -> io::Result<usize> {
+        print!("{}", on_main("Pico-Term").blue());
+        print!("{}", on_main("  │  ").dark_grey());
 
         for i in 0..self.titles.len() {
-            if i == self.menu_option {
-                print!("{}", self.titles[i].on_white());
+            if open && i == self.menu_option {
+                print!("{}", self.titles[i].black().on_white());
             } else {
-                print!("{}", self.titles[i]);
-            }
-
-            if self.menu_option > i {
-                start_pos += self.titles[i].len() + 1;
+                print!("{}", on_main(self.titles[i]).dark_grey());
             }
 
             if i != self.titles.len() - 1 {
-                print!("{}", " ");
+                print!("{}", on_main(&str::repeat(" ", TITLE_GAP)));
             }
         }
 
-        return Ok(start_pos);
+        return Ok(self.title_pos(self.menu_option));
     }
 
-    pub fn handle_key_event(&mut self, key_event: KeyEvent) -> io::Result<ChangedLineType> {
-        let mut changed_line = ChangedLineType::None;
+    pub fn handle_key_event(&mut self, key_event: KeyEvent) -> MenuAction {
+        if let Some(i) = self.title_for_key(key_event) {
+            self.open(i, self.return_mode);
+            return MenuAction::None;
+        }
+
         match key_event.code {
             KeyCode::Up => self.move_up(),
             KeyCode::Down => self.move_down(),
             KeyCode::Right => self.move_right(),
             KeyCode::Left => self.move_left(),
-            _ => changed_line = ChangedLineType::None,
+            KeyCode::Enter => return MenuAction::Run(self.select()),
+            KeyCode::Esc | KeyCode::F(10) => return MenuAction::Close,
+            _ => {}
         }
 
-        Ok(changed_line)
+        MenuAction::None
     }
 
-    pub fn draw(&mut self) -> io::Result<()> {
-        if self.menu_option > 50 {
-            self.reset();
+    // clicking a title switches menus, clicking an item runs it and
+    // clicking anywhere else closes the menu
+    pub fn click(&mut self, column: usize, row: usize, keymap: &Keymap) -> MenuAction {
+        if row == 0 {
+            return match self.title_at(column) {
+                Some(i) => {
+                    self.open(i, self.return_mode);
+                    MenuAction::None
+                }
+                None => MenuAction::Close,
+            };
+        }
+
+        let start = self.title_pos(self.menu_option);
+        if row <= self.items[self.menu_option].len()
+            && column >= start
+            && column < start + self.item_width(keymap)
+        {
+            self.menu_item = row - 1;
+            return MenuAction::Run(self.select());
         }
 
-        let start_pos = self.draw_header()?;
+        MenuAction::Close
+    }
+
+    // wide enough for the longest name and its keys
+    fn item_width(&self, keymap: &Keymap) -> usize {
+        self.items[self.menu_option]
+            .iter()
+            .map(|(name, action)| {
+                char_count(name) + char_count(&keymap.describe_bindings(*action)) + 4
+            })
+            .max()
+            .unwrap_or(0)
+            .max(20)
+    }
+
+    pub fn draw(&mut self, keymap: &Keymap) -> io::Result<()> {
+        let start_pos = self.draw_titles(true)?;
+        let width = self.item_width(keymap);
 
-        execute!(io::stdout(), MoveTo(start_pos as u16, 1))?;
+        for (i, (name, action)) in self.items[self.menu_option].iter().enumerate() {
+            execute!(io::stdout(), MoveTo(start_pos as u16, i as u16 + 1))?;
 
-        let mut i = 0;
-        for text in &self.items[self.menu_option] {
-            let parsed_text;
-            let spacer;
+            let keys = keymap.describe_bindings(*action) + " ";
+            let name = format!(" {}", name);
+            let padding = str::repeat(" ", width - char_count(&name) - char_count(&keys));
 
             if i == self.menu_item {
-                parsed_text = text.on_blue();
-                spacer = " ".on_blue();
+                print!("{}", (name + &padding + &keys).black().on_white());
             } else {
-                parsed_text = text.on_white();
-                spacer = " ".on_white();
+                print!("{}", on_secondary(&(name + &padding)));
+                print!("{}", on_secondary(&keys).dark_grey());
             }
-
-            print!("{}", parsed_text);
-            for _ in 0..20 - text.len() {
-                print!("{}", spacer);
-            }
-
-            i += 1;
-            execute!(io::stdout(), MoveTo(start_pos as u16, i as u16 + 1))?;
         }
 
         Ok(())
     }
 
-    pub fn select(&mut self) -> &str {
-        let result = self.items[self.menu_option][self.menu_item];
-        self.hide();
-        return result;
+    pub fn select(&mut self) -> Action {
+        self.items[self.menu_option][self.menu_item].1
     }
 }
@@ -0,0 +1,172 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::style::Stylize;
+
+use crate::actions::{Action, ACTIONS};
+use crate::file_finder::fuzzy_match;
+use crate::functions::char_count;
+use crate::keymap::Keymap;
+use crate::{on_main, on_secondary, Mode};
+
+// runs any action by typing part of its name, opened with F1 or CTRL+SHIFT+P
+pub struct Palette {
+    query: String,
+    // index into ACTIONS and the matched char positions, best match first
+    matches: Vec<(usize, Vec<usize>)>,
+    selected: usize,
+    scroll: usize,
+    return_mode: Mode,
+}
+
+impl Palette {
+    pub fn new() -> Self {
+        Self {
+            query: String::new(),
+            matches: Vec::new(),
+            selected: 0,
+            scroll: 0,
+            return_mode: Mode::WriteMode,
+        }
+    }
+
+    pub fn open(&mut self, return_mode: Mode) {
+        self.query.clear();
+        self.return_mode = return_mode;
+        self.update();
+    }
+
+    pub fn get_return_mode(&self) -> Mode {
+        self.return_mode
+    }
+
+    pub fn selected_action(&self) -> Option<Action> {
+        let (i, _) = self.matches.get(self.selected)?;
+        Some(ACTIONS[*i].0)
+    }
+
+    // returns true once the palette is closed, ENTER runs the selected action
+    pub fn handle_key_event(&mut self, key_event: KeyEvent, editor_height: &usize) -> bool {
+        let last = self.matches.len().saturating_sub(1);
+
+        match key_event.code {
+            KeyCode::Esc | KeyCode::Enter => return true,
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected = (self.selected + 1).min(last),
+            KeyCode::PageUp => self.selected = self.selected.saturating_sub(*editor_height),
+            KeyCode::PageDown => self.selected = (self.selected + editor_height).min(last),
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.update();
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.update();
+            }
+            _ => {}
+        }
+
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + editor_height {
+            self.scroll = self.selected + 1 - editor_height;
+        }
+
+        false
+    }
+
+    fn update(&mut self) {
+        let query: Vec<char> = self
+            .query
+            .chars()
+            .map(|c| c.to_lowercase().next().unwrap_or(c))
+            .collect();
+
+        let mut scored: Vec<(i64, usize, Vec<usize>)> = ACTIONS
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (_, _, name))| {
+                let (score, positions) = fuzzy_match(&query, name)?;
+                Some((score, i, positions))
+            })
+            .collect();
+
+        // without a query the actions stay in their usual order
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+        self.matches = scored
+            .into_iter()
+            .map(|(_, i, positions)| (i, positions))
+            .collect();
+        self.selected = 0;
+        self.scroll = 0;
+    }
+
+    // the actions with their keys on the right
+    pub fn draw(&self, width: &usize, editor_height: &usize, keymap: &Keymap) {
+        let title = format!(" Commands: {} of {}", self.matches.len(), ACTIONS.len());
+        let title: String = title.chars().take(*width).collect();
+
+        println!();
+        print!("{}", on_secondary(&title));
+        println!(
+            "{}",
+            on_secondary(&str::repeat(" ", width - char_count(&title)))
+        );
+        println!("{}", on_secondary(&str::repeat(" ", *width)));
+
+        for row in 0..*editor_height {
+            let i = self.scroll + row;
+            let Some((action, positions)) = self.matches.get(i) else {
+                println!("{}", on_secondary(&str::repeat(" ", *width)));
+                continue;
+            };
+            let (action, _, name) = ACTIONS[*action];
+
+            let keys = keymap.describe_bindings(action) + " ";
+            let keys: String = keys.chars().take(width.saturating_sub(2)).collect();
+            let space = width - char_count(&keys);
+            let written = self.draw_name(name, positions, i, space);
+
+            let padding = str::repeat(" ", space - written);
+            if i == self.selected {
+                print!("{}", (padding + &keys).black().on_white());
+            } else {
+                print!("{}", on_secondary(&padding));
+                print!("{}", on_secondary(&keys).dark_grey());
+            }
+            println!();
+        }
+    }
+
+    // the name with the matched characters marked, returns its width
+    fn draw_name(&self, name: &str, positions: &[usize], i: usize, width: usize) -> usize {
+        let chars: Vec<char> = format!(" {}", name).chars().take(width).collect();
+
+        for (n, c) in chars.iter().enumerate() {
+            let matched = n > 0 && positions.contains(&(n - 1));
+            let text = c.to_string();
+
+            match (i == self.selected, matched) {
+                (true, true) => print!("{}", text.black().on_white().bold()),
+                (true, false) => print!("{}", text.black().on_white()),
+                (false, true) => print!("{}", on_secondary(&text).yellow().bold()),
+                (false, false) => print!("{}", on_secondary(&text)),
+            }
+        }
+
+        chars.len()
+    }
+
+    pub fn draw_input(&self, width: &usize) {
+        let prefix = "COMMAND /> ";
+
+        print!("{}", prefix.red());
+        print!("{}", on_main(&self.query));
+        print!("{}", ' '.on_red());
+
+        let written = prefix.len() + char_count(&self.query) + 1;
+        print!(
+            "{}",
+            on_main(&str::repeat(" ", width.saturating_sub(written)))
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::style::Stylize;
+use ignore::WalkBuilder;
+use regex::{Regex, RegexBuilder};
+
+use crate::encoding;
+use crate::functions::char_count;
+use crate::on_secondary;
+
+// stops a search for something very common from filling up the memory
+const MAX_HITS: usize = 2000;
+// bigger files are most likely not source code
+const MAX_FILE_SIZE: u64 = 8 * 1024 * 1024;
+
+pub struct Hit {
+    pub path: PathBuf,
+    pub line: usize,
+    pub char: usize,
+    // the whole line the match is on
+    text: String,
+}
+
+// matches of a pattern in every file under a directory, shown as a list
+pub struct ProjectSearch {
+    title: String,
+    root: PathBuf,
+    hits: Vec<Hit>,
+    selected: usize,
+    scroll: usize,
+}
+
+impl ProjectSearch {
+    pub fn new() -> Self {
+        Self {
+            title: String::new(),
+            root: PathBuf::new(),
+            hits: Vec::new(),
+            selected: 0,
+            scroll: 0,
+        }
+    }
+
+    // "/pattern/" or "/pattern/i" is a regex, anything else is searched as is
+    pub fn parse_pattern(args: &str) -> Result<Regex, String> {
+        let args = args.trim();
+        if args.is_empty() {
+            return Err(String::from("usage: search <text> or search /regex/[i]"));
+        }
+
+        let (pattern, ignore_case) = match args.strip_prefix('/') {
+            Some(rest) if rest.ends_with('/') => (rest[..rest.len() - 1].to_owned(), false),
+            Some(rest) if rest.ends_with("/i") => (rest[..rest.len() - 2].to_owned(), true),
+            _ => (regex::escape(args), false),
+        };
+
+        RegexBuilder::new(&pattern)
+            .case_insensitive(ignore_case)
+            .build()
+            .map_err(|error| match error {
+                regex::Error::Syntax(message) => {
+                    "Invalid pattern: ".to_owned() + message.lines().last().unwrap_or("")
+                }
+                _ => String::from("Pattern is too big"),
+            })
+    }
+
+    // walks the directory, skipping whatever .gitignore and hidden files cover
+    pub fn run(pattern: &Regex, root: PathBuf) -> Self {
+        let mut hits = Vec::new();
+        let mut files = 0;
+
+        let walker = WalkBuilder::new(&root).require_git(false).build();
+        for entry in walker.flatten() {
+            if hits.len() >= MAX_HITS {
+                break;
+            }
+            if !entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file())
+            {
+                continue;
+            }
+
+            let found = search_file(pattern, entry.path(), &mut hits);
+            if found > 0 {
+                files += 1;
+            }
+        }
+        hits.truncate(MAX_HITS);
+
+        let title = match hits.len() {
+            MAX_HITS => format!("More than {} matches of '{}'", MAX_HITS, pattern.as_str()),
+            count => format!(
+                "{} matches of '{}' in {} files",
+                count,
+                pattern.as_str(),
+                files
+            ),
+        };
+
+        Self {
+            title,
+            root,
+            hits,
+            selected: 0,
+            scroll: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hits.is_empty()
+    }
+
+    pub fn selected(&self) -> Option<&Hit> {
+        self.hits.get(self.selected)
+    }
+
+    // returns true once the list should be closed, ENTER opens the selected hit
+    pub fn handle_key_event(&mut self, key_event: KeyEvent, editor_height: &usize) -> bool {
+        let last = self.hits.len().saturating_sub(1);
+
+        match key_event.code {
+            KeyCode::Esc | KeyCode::Enter => return true,
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected = (self.selected + 1).min(last),
+            KeyCode::PageUp => self.selected = self.selected.saturating_sub(*editor_height),
+            KeyCode::PageDown => self.selected = (self.selected + editor_height).min(last),
+            KeyCode::Home => self.selected = 0,
+            KeyCode::End => self.selected = last,
+            _ => {}
+        }
+
+        self.keep_visible(editor_height);
+        false
+    }
+
+    // keeps the selection on screen, also after the terminal got smaller
+    pub fn keep_visible(&mut self, editor_height: &usize) {
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + editor_height {
+            self.scroll = self.selected + 1 - editor_height;
+        }
+    }
+
+    pub fn draw(&self, width: &usize, editor_height: &usize) {
+        println!();
+        print!("{}", on_secondary(" "));
+        print!("{}", on_secondary(&self.title));
+        println!("{}", on_secondary("  "));
+        println!("{}", on_secondary(&str::repeat(" ", *width)));
+
+        for i in self.scroll..self.scroll + editor_height {
+            let Some(hit) = self.hits.get(i) else {
+                println!("{}", on_secondary(&str::repeat(" ", *width)));
+                continue;
+            };
+
+            let path = hit.path.strip_prefix(&self.root).unwrap_or(&hit.path);
+            let location = format!(" {}:{} ", path.display(), hit.line + 1);
+            let location: String = location.chars().take(*width).collect();
+
+            let space = width.saturating_sub(char_count(&location));
+            let text: String = hit.text.trim().chars().take(space).collect();
+            let padding = str::repeat(" ", space - char_count(&text));
+
+            if i == self.selected {
+                print!("{}", location.black().on_white());
+                print!("{}", (text + &padding).on_dark_grey());
+            } else {
+                print!("{}", on_secondary(&location).cyan());
+                print!("{}", on_secondary(&(text + &padding)));
+            }
+            println!();
+        }
+    }
+}
+
+// adds the matches in one file, returns how many there were
+fn search_file(pattern: &Regex, path: &Path, hits: &mut Vec<Hit>) -> usize {
+    if fs::metadata(path).map_or(true, |metadata| metadata.len() > MAX_FILE_SIZE) {
+        return 0;
+    }
+    let Ok(bytes) = fs::read(path) else {
+        return 0;
+    };
+    if encoding::is_binary(&bytes) {
+        return 0;
+    }
+
+    let (file_encoding, bom_len) = encoding::detect(&bytes);
+    let contents = file_encoding.decode(&bytes[bom_len..]);
+    let before = hits.len();
+
+    for (i, line) in contents.split('\n').enumerate() {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+
+        for found in pattern.find_iter(line) {
+            hits.push(Hit {
+                path: path.to_path_buf(),
+                line: i,
+                char: char_count(&line[..found.start()]),
+                text: line.to_owned(),
+            });
+        }
+    }
+
+    hits.len() - before
+}
@@ -0,0 +1,134 @@
+use std::path::PathBuf;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::style::Stylize;
+
+use crate::{on_main, Mode};
+
+#[derive(Clone)]
+pub enum PromptAction {
+    Quit,
+    NewFile,
+    OpenFile,
+    RecoverSwap,
+    // saving is set when the check happened right before writing the file,
+    // then is what was waiting for the save, like quitting
+    ExternalChange {
+        saving: bool,
+        then: Option<Box<PromptAction>>,
+    },
+    // the preview before replacing all matches
+    ReplaceAll,
+    // confirm-each replace, asked for every match
+    ReplaceMatch,
+    // a hit of the project search in another file
+    OpenResult,
+    // a file picked in the explorer or opened with the e command
+    OpenEntry(PathBuf),
+    DeleteEntry(PathBuf),
+}
+
+pub struct Prompt {
+    question: String,
+    options: Vec<(char, &'static str)>,
+    action: PromptAction,
+    return_mode: Mode,
+}
+
+impl Prompt {
+    pub fn new() -> Self {
+        Self {
+            question: String::new(),
+            options: Vec::new(),
+            action: PromptAction::Quit,
+            return_mode: Mode::WriteMode,
+        }
+    }
+
+    pub fn open(
+        &mut self,
+        question: String,
+        options: Vec<(char, &'static str)>,
+        action: PromptAction,
+        return_mode: Mode,
+    ) {
+        self.question = question;
+        self.options = options;
+        self.action = action;
+        self.return_mode = return_mode;
+    }
+
+    // asks to save / discard / cancel before the buffer gets thrown away
+    pub fn open_unsaved(&mut self, file_name: &str, action: PromptAction, return_mode: Mode) {
+        self.open(
+            "'".to_owned() + file_name + "' has unsaved changes.",
+            vec![('s', "Save"), ('d', "Discard"), ('c', "Cancel")],
+            action,
+            return_mode,
+        );
+    }
+
+    // the file was changed by another program while it was open, a save
+    // can be cancelled so ESC doesn't overwrite the file on disk
+    pub fn open_external_change(
+        &mut self,
+        file_name: &str,
+        can_diff: bool,
+        saving: bool,
+        then: Option<PromptAction>,
+        return_mode: Mode,
+    ) {
+        let mut options = vec![('r', "Reload")];
+        if can_diff {
+            options.push(('d', "Show diff"));
+        }
+        options.push(('k', "Keep mine"));
+        if saving {
+            options.push(('c', "Cancel"));
+        }
+
+        self.open(
+            "'".to_owned() + file_name + "' was changed on disk.",
+            options,
+            PromptAction::ExternalChange {
+                saving,
+                then: then.map(Box::new),
+            },
+            return_mode,
+        );
+    }
+
+    pub fn get_action(&self) -> &PromptAction {
+        &self.action
+    }
+
+    pub fn get_return_mode(&self) -> Mode {
+        self.return_mode
+    }
+
+    // returns the key of the chosen option, ESC picks the last one (cancel)
+    pub fn handle_key_event(&self, key_event: KeyEvent) -> Option<char> {
+        match key_event.code {
+            KeyCode::Char(c) => self
+                .options
+                .iter()
+                .map(|(key, _)| *key)
+                .find(|key| *key == c.to_ascii_lowercase()),
+            KeyCode::Esc => self.options.last().map(|(key, _)| *key),
+            _ => None,
+        }
+    }
+
+    pub fn draw(&self, width: &usize) {
+        let mut text = " ".to_owned() + &self.question + " ";
+        for (key, label) in &self.options {
+            text.push_str(&format!(" [{}] {}", key.to_ascii_uppercase(), label));
+        }
+
+        let text: String = text.chars().take(*width).collect();
+        let padding = width - text.chars().count();
+
+        print!("{}", text.black().on_yellow());
+        print!("{}", on_main(&str::repeat(" ", padding)));
+    }
+}
@@ -0,0 +1,343 @@
+use regex::{Captures, Regex, RegexBuilder};
+
+use crate::functions::{byte_index, char_count};
+
+pub enum ReplaceMode {
+    // the next match after the cursor
+    One,
+    All,
+    // asks before every match
+    Confirm,
+}
+
+// a running `replace /pattern/replacement/flags` command, the lines are
+// searched as one text so replacements can add or join lines
+pub struct Replace {
+    regex: Regex,
+    replacement: String,
+    pub mode: ReplaceMode,
+    // byte offset in the text where the next match is looked for
+    position: usize,
+    // byte range of the match waiting for confirmation
+    current: Option<(usize, usize)>,
+    pub replaced: usize,
+}
+
+impl Replace {
+    // flags: g replaces all matches, c asks for each one, i ignores case
+    pub fn parse(args: &str) -> Result<Self, String> {
+        let usage = "usage: replace /pattern/replacement/[gci]";
+
+        let parts = split_unescaped(args.trim_start(), '/');
+        let (pattern, replacement, flags) = match parts.as_slice() {
+            [empty, pattern, replacement] if empty.is_empty() => (pattern, replacement, ""),
+            [empty, pattern, replacement, flags] if empty.is_empty() => {
+                (pattern, replacement, flags.as_str())
+            }
+            _ => return Err(String::from(usage)),
+        };
+
+        if let Some(flag) = flags.chars().find(|flag| !"gci".contains(*flag)) {
+            return Err(format!("Unknown flag '{}', {}", flag, usage));
+        }
+
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(flags.contains('i'))
+            .multi_line(true)
+            .build()
+            .map_err(|error| match error {
+                regex::Error::Syntax(message) => {
+                    "Invalid pattern: ".to_owned() + message.lines().last().unwrap_or("")
+                }
+                _ => String::from("Pattern is too big"),
+            })?;
+
+        let mode = match (flags.contains('c'), flags.contains('g')) {
+            (true, _) => ReplaceMode::Confirm,
+            (false, true) => ReplaceMode::All,
+            (false, false) => ReplaceMode::One,
+        };
+
+        Ok(Self {
+            regex,
+            replacement: unescape(replacement),
+            mode,
+            position: 0,
+            current: None,
+            replaced: 0,
+        })
+    }
+
+    // e.g. "Replaced 3 matches of 'foo'"
+    pub fn summary(&self) -> String {
+        let matches = match self.replaced {
+            1 => "match",
+            _ => "matches",
+        };
+        format!(
+            "Replaced {} {} of '{}'",
+            self.replaced,
+            matches,
+            self.regex.as_str()
+        )
+    }
+
+    // number of matches from where the replacing continues
+    pub fn count(&self, lines: &[String]) -> usize {
+        let text = lines.join("\n");
+        self.matches_from(&text, self.position).len()
+    }
+
+    // starts looking for matches at a cursor position
+    pub fn start_at(&mut self, lines: &[String], line: usize, char: usize) {
+        self.position = text_offset(lines, line, char);
+        self.current = None;
+    }
+
+    // finds the next match and returns where it starts as a cursor position
+    pub fn next(&mut self, lines: &[String]) -> Option<(usize, usize)> {
+        let text = lines.join("\n");
+
+        self.current = None;
+        if self.position > text.len() {
+            return None;
+        }
+
+        let found = self.regex.find_at(&text, self.position)?;
+        self.current = Some((found.start(), found.end()));
+        Some(cursor_of(&text, found.start()))
+    }
+
+    // replaces the match found by next, returns where the replacement ends
+    pub fn replace_current(&mut self, lines: &mut Vec<String>) -> Option<(usize, usize)> {
+        let (start, end) = self.current.take()?;
+        let text = lines.join("\n");
+        let captures = self.regex.captures_at(&text, start)?;
+
+        let mut replacement = String::new();
+        captures.expand(&self.replacement, &mut replacement);
+
+        let new_text = text[..start].to_owned() + &replacement + &text[end..];
+        *lines = new_text.split('\n').map(String::from).collect();
+        self.replaced += 1;
+
+        let replacement_end = start + replacement.len();
+        self.position = self.advance(&new_text, replacement_end, start == end);
+        Some(cursor_of(&new_text, replacement_end))
+    }
+
+    pub fn skip_current(&mut self, lines: &[String]) {
+        if let Some((start, end)) = self.current.take() {
+            self.position = self.advance(&lines.join("\n"), end, start == end);
+        }
+    }
+
+    // replaces every match from the current position on
+    pub fn replace_rest(&mut self, lines: &mut Vec<String>) {
+        let text = lines.join("\n");
+        let start = match self.current.take() {
+            Some((start, _)) => start,
+            None => self.position,
+        };
+
+        let mut new_text = String::new();
+        let mut last = 0;
+        for captures in self.matches_from(&text, start) {
+            let found = captures.get(0).unwrap();
+            new_text.push_str(&text[last..found.start()]);
+            captures.expand(&self.replacement, &mut new_text);
+            last = found.end();
+            self.replaced += 1;
+        }
+        new_text.push_str(&text[last..]);
+
+        *lines = new_text.split('\n').map(String::from).collect();
+        self.position = new_text.len() + 1;
+    }
+
+    // the match waiting for confirmation as line, first and last char
+    pub fn highlight(&self, lines: &[String]) -> Option<(usize, usize, usize)> {
+        let (start, end) = self.current?;
+        let text = lines.join("\n");
+        let (line, first) = cursor_of(&text, start);
+
+        // matches across lines only get marked on their first one
+        let line_end = text[start..].find('\n').map_or(text.len(), |i| start + i);
+        let len = char_count(&text[start..end.min(line_end)]);
+        Some((line, first, first + len.max(1)))
+    }
+
+    // the matches that start at or after `start`, searched from there so a
+    // match that begins before it can't hide one that begins after it
+    fn matches_from<'t>(&self, text: &'t str, start: usize) -> Vec<Captures<'t>> {
+        let mut matches = Vec::new();
+        let mut at = start;
+        let mut previous_end = None;
+
+        while at <= text.len() {
+            let Some(captures) = self.regex.captures_at(text, at) else {
+                break;
+            };
+            let found = captures.get(0).unwrap();
+
+            // like captures_iter, no empty match right where the last one ended
+            if found.is_empty() && previous_end == Some(found.start()) {
+                at = self.advance(text, found.start(), true);
+                continue;
+            }
+
+            previous_end = Some(found.end());
+            at = self.advance(text, found.end(), found.is_empty());
+            matches.push(captures);
+        }
+
+        matches
+    }
+
+    // empty matches would be found again at the same spot
+    fn advance(&self, text: &str, position: usize, empty_match: bool) -> usize {
+        match empty_match {
+            true => text[position..]
+                .chars()
+                .next()
+                .map_or(text.len() + 1, |c| position + c.len_utf8()),
+            false => position,
+        }
+    }
+}
+
+// byte offset of a cursor position in the lines joined with newlines
+fn text_offset(lines: &[String], line: usize, char: usize) -> usize {
+    let before: usize = lines[..line].iter().map(|line| line.len() + 1).sum();
+    before + byte_index(&lines[line], char)
+}
+
+fn cursor_of(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (line, char_count(&before[line_start..]))
+}
+
+// splits on a separator that isn't escaped with a backslash, \/ becomes /
+fn split_unescaped(text: &str, separator: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(next) if next == separator => parts.last_mut().unwrap().push(next),
+                Some(next) => {
+                    parts.last_mut().unwrap().push('\\');
+                    parts.last_mut().unwrap().push(next);
+                }
+                None => parts.last_mut().unwrap().push('\\'),
+            },
+            _ if c == separator => parts.push(String::new()),
+            _ => parts.last_mut().unwrap().push(c),
+        }
+    }
+
+    parts
+}
+
+// \n and \t can't be typed into the console
+fn unescape(replacement: &str) -> String {
+    replacement.replace("\\n", "\n").replace("\\t", "\t")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.split('\n').map(String::from).collect()
+    }
+
+    // replaces every match from a cursor position on
+    fn replace_all(args: &str, text: &str, line: usize, char: usize) -> String {
+        let mut replace = Replace::parse(args).unwrap();
+        let mut lines = lines(text);
+        replace.start_at(&lines, line, char);
+        replace.replace_rest(&mut lines);
+        lines.join("\n")
+    }
+
+    #[test]
+    fn parses_patterns_and_flags() {
+        assert!(matches!(
+            Replace::parse("/a/b/").unwrap().mode,
+            ReplaceMode::One
+        ));
+        assert!(matches!(
+            Replace::parse("/a/b/g").unwrap().mode,
+            ReplaceMode::All
+        ));
+        assert!(matches!(
+            Replace::parse("/a/b/gc").unwrap().mode,
+            ReplaceMode::Confirm
+        ));
+
+        let replace = Replace::parse("/a\\/b/c\\/d\\n/").unwrap();
+        assert_eq!(replace.regex.as_str(), "a/b");
+        assert_eq!(replace.replacement, "c/d\n");
+
+        // the closing slash can be left off
+        assert!(matches!(
+            Replace::parse("/a/b").unwrap().mode,
+            ReplaceMode::One
+        ));
+        assert!(Replace::parse("/a").is_err());
+        assert!(Replace::parse("a/b/").is_err());
+        assert_eq!(
+            Replace::parse("/a/b/x").err().unwrap(),
+            "Unknown flag 'x', usage: replace /pattern/replacement/[gci]"
+        );
+        assert!(Replace::parse("/(/b/")
+            .err()
+            .unwrap()
+            .starts_with("Invalid pattern"));
+    }
+
+    #[test]
+    fn expands_capture_groups() {
+        assert_eq!(
+            replace_all("/(\\w+)=(\\w+)/$2=$1/g", "a=b c=d", 0, 0),
+            "b=a d=c"
+        );
+        assert_eq!(
+            replace_all("/(?P<key>\\w+):/${key}_x:/g", "a: b:", 0, 0),
+            "a_x: b_x:"
+        );
+        assert_eq!(replace_all("/b/B/gi", "abcB", 0, 0), "aBcB");
+        assert_eq!(replace_all("/, /\\n/g", "a, b", 0, 0), "a\nb");
+    }
+
+    #[test]
+    fn replaces_only_from_the_cursor_on() {
+        assert_eq!(replace_all("/a/x/g", "a a\na a", 1, 0), "a a\nx x");
+        // the match starting before the cursor doesn't hide the one after it
+        assert_eq!(replace_all("/aa/x/g", "aaa", 0, 1), "ax");
+        assert_eq!(replace_all("/^/> /g", "a\nb", 1, 0), "a\n> b");
+        assert_eq!(replace_all("/x*/-/g", "axb", 0, 0), "-a-b-");
+    }
+
+    #[test]
+    fn counts_and_replaces_one_at_a_time() {
+        let mut replace = Replace::parse("/o/0/c").unwrap();
+        let mut lines = lines("foo\nboo");
+        replace.start_at(&lines, 0, 2);
+        assert_eq!(replace.count(&lines), 3);
+
+        assert_eq!(replace.next(&lines), Some((0, 2)));
+        assert_eq!(replace.replace_current(&mut lines), Some((0, 3)));
+        assert_eq!(replace.next(&lines), Some((1, 1)));
+        replace.skip_current(&lines);
+        assert_eq!(replace.next(&lines), Some((1, 2)));
+        replace.replace_current(&mut lines);
+
+        assert_eq!(lines, ["fo0", "bo0"]);
+        assert_eq!(replace.summary(), "Replaced 2 matches of 'o'");
+    }
+}
@@ -0,0 +1,255 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::style::{StyledContent, Stylize};
+use regex::RegexBuilder;
+
+use crate::{functions::char_count, on_main, Mode};
+
+// incremental search through the buffer, opened with CTRL+F or /
+pub struct Search {
+    query: String,
+    case_sensitive: bool,
+    whole_word: bool,
+    // line, first char and length in chars of every match
+    matches: Vec<(usize, usize, usize)>,
+    current: Option<usize>,
+    // where the cursor was when the search got opened, ESC goes back there
+    origin: (usize, usize),
+    return_mode: Mode,
+}
+
+impl Search {
+    pub fn new() -> Self {
+        Self {
+            query: String::new(),
+            case_sensitive: false,
+            whole_word: false,
+            matches: Vec::new(),
+            current: None,
+            origin: (0, 0),
+            return_mode: Mode::WriteMode,
+        }
+    }
+
+    // the last query stays, so searching again jumps to its next match
+    pub fn open(
+        &mut self,
+        return_mode: Mode,
+        lines: &[String],
+        current_line: &mut usize,
+        current_char: &mut usize,
+    ) {
+        self.return_mode = return_mode;
+        self.origin = (*current_line, *current_char);
+        self.update(lines, current_line, current_char);
+    }
+
+    pub fn get_return_mode(&self) -> Mode {
+        self.return_mode
+    }
+
+    // returns true once the search is closed
+    pub fn handle_key_event(
+        &mut self,
+        key_event: KeyEvent,
+        lines: &[String],
+        current_line: &mut usize,
+        current_char: &mut usize,
+    ) -> bool {
+        match key_event.code {
+            KeyCode::Enter => return true,
+            KeyCode::Esc => {
+                (*current_line, *current_char) = self.origin;
+                return true;
+            }
+            KeyCode::Down => self.step(true, current_line, current_char),
+            KeyCode::Up => self.step(false, current_line, current_char),
+            KeyCode::Char('c') if key_event.modifiers == KeyModifiers::ALT => {
+                self.case_sensitive = !self.case_sensitive;
+                self.update(lines, current_line, current_char);
+            }
+            KeyCode::Char('w') if key_event.modifiers == KeyModifiers::ALT => {
+                self.whole_word = !self.whole_word;
+                self.update(lines, current_line, current_char);
+            }
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.update(lines, current_line, current_char);
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.update(lines, current_line, current_char);
+            }
+            _ => {}
+        }
+
+        false
+    }
+
+    // moves the cursor to the next or previous match, wrapping around
+    pub fn step(&mut self, forward: bool, current_line: &mut usize, current_char: &mut usize) {
+        let count = self.matches.len();
+        if count == 0 {
+            return;
+        }
+
+        let next = match (self.current, forward) {
+            (Some(current), true) => (current + 1) % count,
+            (Some(current), false) => (current + count - 1) % count,
+            (None, _) => 0,
+        };
+        self.select(next, current_line, current_char);
+    }
+
+    // searches the last query again from the cursor, for n and N in edit mode
+    pub fn jump(
+        &mut self,
+        forward: bool,
+        lines: &[String],
+        current_line: &mut usize,
+        current_char: &mut usize,
+    ) {
+        self.matches = self.find_all(lines);
+        self.current = None;
+
+        let cursor = (*current_line, *current_char);
+        let found = match forward {
+            true => self
+                .matches
+                .iter()
+                .position(|(line, start, _)| (*line, *start) > cursor)
+                .or((!self.matches.is_empty()).then_some(0)),
+            false => self
+                .matches
+                .iter()
+                .rposition(|(line, start, _)| (*line, *start) < cursor)
+                .or(self.matches.len().checked_sub(1)),
+        };
+
+        if let Some(i) = found {
+            self.select(i, current_line, current_char);
+        }
+    }
+
+    // e.g. "Match 2 of 5" for the status bar
+    pub fn describe(&self) -> String {
+        match self.current {
+            _ if self.query.is_empty() => String::new(),
+            Some(current) => format!("Match {} of {}", current + 1, self.matches.len()),
+            None => format!("No matches for '{}'", self.query),
+        }
+    }
+
+    // matches as line, start and end char, the flag marks the selected one
+    pub fn highlights(&self) -> Vec<(usize, usize, usize, bool)> {
+        self.matches
+            .iter()
+            .enumerate()
+            .map(|(i, (line, start, len))| (*line, *start, start + len, Some(i) == self.current))
+            .collect()
+    }
+
+    // searches again after the query or the options changed, starting at the origin
+    fn update(&mut self, lines: &[String], current_line: &mut usize, current_char: &mut usize) {
+        self.matches = self.find_all(lines);
+        self.current = None;
+
+        let (line, char) = self.origin;
+        let first = self
+            .matches
+            .iter()
+            .position(|(match_line, start, _)| (*match_line, *start) >= (line, char));
+
+        match first {
+            Some(i) => self.select(i, current_line, current_char),
+            None if !self.matches.is_empty() => self.select(0, current_line, current_char),
+            None => (*current_line, *current_char) = self.origin,
+        }
+    }
+
+    fn select(&mut self, i: usize, current_line: &mut usize, current_char: &mut usize) {
+        let (line, start, _) = self.matches[i];
+        self.current = Some(i);
+        *current_line = line;
+        *current_char = start;
+    }
+
+    fn find_all(&self, lines: &[String]) -> Vec<(usize, usize, usize)> {
+        if self.query.is_empty() {
+            return Vec::new();
+        }
+
+        let regex = match RegexBuilder::new(&regex::escape(&self.query))
+            .case_insensitive(!self.case_sensitive)
+            .build()
+        {
+            Ok(regex) => regex,
+            Err(_) => return Vec::new(),
+        };
+
+        let mut matches = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            for found in regex.find_iter(line) {
+                if self.whole_word && !is_whole_word(line, found.start(), found.end()) {
+                    continue;
+                }
+
+                let start = char_count(&line[..found.start()]);
+                matches.push((i, start, char_count(found.as_str())));
+            }
+        }
+
+        matches
+    }
+
+    pub fn draw(&self, width: &usize) {
+        let prefix = "FIND /> ";
+        let counter = match self.current {
+            Some(current) => format!("{}/{}", current + 1, self.matches.len()),
+            None if self.query.is_empty() => String::new(),
+            None => String::from("0/0"),
+        };
+
+        // toggles and counter on the right, the query gets cut from the left when it doesn't fit
+        let right = " Aa ".len() + 1 + " Word ".len() + 2 + counter.len() + 1;
+        let space = width.saturating_sub(prefix.len() + 1 + right);
+        let query: String = self
+            .query
+            .chars()
+            .skip(char_count(&self.query).saturating_sub(space))
+            .collect();
+
+        print!("{}", prefix.red());
+        print!("{}", on_main(&query));
+        print!("{}", ' '.on_red());
+
+        let written = prefix.len() + char_count(&query) + 1;
+        print!(
+            "{}",
+            on_main(&str::repeat(" ", width.saturating_sub(written + right)))
+        );
+
+        print!("{}", toggle(" Aa ", self.case_sensitive));
+        print!("{}", on_main(" "));
+        print!("{}", toggle(" Word ", self.whole_word));
+        print!("{}", on_main("  "));
+        print!("{}", on_main(&counter).dark_grey());
+        print!("{}", on_main(" "));
+    }
+}
+
+// active toggles stand out, ALT+C and ALT+W flip them
+fn toggle(label: &str, active: bool) -> StyledContent<&str> {
+    match active {
+        true => label.black().on_white(),
+        false => on_main(label).dark_grey(),
+    }
+}
+
+fn is_whole_word(line: &str, start: usize, end: usize) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+
+    let before = line[..start].chars().next_back();
+    let after = line[end..].chars().next();
+
+    !before.is_some_and(is_word) && !after.is_some_and(is_word)
+}
@@ -0,0 +1,49 @@
+use crate::indent::Indent;
+
+#[derive(Clone)]
+pub struct Settings {
+    // spaces the tab key inserts and how wide tabs are drawn, for files
+    // whose indentation can't be detected
+    pub tab_width: usize,
+    // indent those files with tabs instead
+    pub use_tabs: bool,
+    // lines the mouse wheel scrolls at a time
+    pub scroll_step: usize,
+    // digits reserved for line numbers, the gutter is 3 columns wider
+    pub line_number_width: usize,
+    // saves the file whenever the terminal loses focus
+    pub save_on_unfocus: bool,
+    // seconds without input before the file gets saved, 0 turns it off
+    pub autosave_after: u64,
+    // seconds between swap file updates while there are unsaved changes
+    pub swap_interval: u64,
+    // typing an opening bracket or quote adds the closing one
+    pub auto_close: bool,
+}
+
+impl Settings {
+    pub fn new() -> Self {
+        Self {
+            tab_width: 4,
+            use_tabs: false,
+            scroll_step: 2,
+            line_number_width: 4,
+            save_on_unfocus: false,
+            autosave_after: 0,
+            swap_interval: 4,
+            auto_close: true,
+        }
+    }
+
+    pub fn default_indent(&self) -> Indent {
+        match self.use_tabs {
+            true => Indent::tabs(self.tab_width),
+            false => Indent::spaces(self.tab_width),
+        }
+    }
+
+    // columns in front of the text, the line number and " │ "
+    pub fn gutter_width(&self) -> usize {
+        self.line_number_width + 3
+    }
+}
@@ -2,16 +2,19 @@ use std::io;
 
 use crossterm::style::{StyledContent, Stylize};
 
-use crate::{functions::move_to, on_main, Mode};
+use crate::{actions::Action, buffer::Buffer, functions::move_to, keymap::Keymap, on_main, Mode};
 
 pub fn draw_skeleton(
     width: &usize,
     height: &usize,
+    buffer: &Buffer,
     info_text: &str,
     current_mode: &Mode,
     current_line: &usize,
     current_char: &usize,
     current_scroll: &usize,
+    warning: &str,
+    keymap: &Keymap,
 ) -> io::Result<()> {
     for y in 0..height - 1 {
         let string = str::repeat(" ", *width);
@@ -19,56 +22,129 @@ pub fn draw_skeleton(
     }
 
     let mode_status: StyledContent<&str>;
-    let help_text: &str;
+    let mut help_text: String;
+    // the keys for switching away, as they are bound in the keymap
+    let keys_for = |action: Action, what: &str| {
+        keymap
+            .describe_binding(action, *current_mode)
+            .map(|keys| keys + " - " + what)
+    };
     let mut spacer_len = width.clone();
-    let line_info = generate_line_info(current_line, current_char, current_scroll);
+    let line_info = generate_line_info(buffer, current_line, current_char, current_scroll);
+    // warnings stay in front of the line info until they are fixed
+    let warning = match warning.is_empty() {
+        true => String::new(),
+        false => warning.to_owned() + " │ ",
+    };
+    spacer_len = spacer_len.saturating_sub(warning.chars().count());
 
     match current_mode {
         Mode::WriteMode => {
             let text = "WRITE MODE";
             mode_status = text.white().on_blue();
             spacer_len -= text.len();
-            help_text = "ALT+J - Edit Mode"
+            help_text = keys_for(Action::EditMode, "Edit Mode").unwrap_or_default()
         }
         Mode::EditMode => {
             let text = "EDIT MODE";
             mode_status = text.on_green().white();
             spacer_len -= text.len();
-            help_text = "Q - Write Mode"
+            help_text = keys_for(Action::WriteMode, "Write Mode").unwrap_or_default()
+        }
+        Mode::HexMode => {
+            let text = "HEX MODE";
+            mode_status = text.on_magenta().white();
+            spacer_len -= text.len();
+            help_text = keys_for(Action::ToggleHex, "Text")
+                .map(|text| text + ", ")
+                .unwrap_or_default()
+                + "TAB - Column"
+        }
+        Mode::DiffMode => {
+            let text = "DIFF";
+            mode_status = text.on_cyan().white();
+            spacer_len -= text.len();
+            help_text = String::from("ESC - Back");
+        }
+        Mode::ResultsMode => {
+            let text = "SEARCH";
+            mode_status = text.on_cyan().white();
+            spacer_len -= text.len();
+            help_text = String::from("ENTER - Open, ESC - Back");
+        }
+        Mode::ExplorerMode => {
+            let text = "EXPLORER";
+            mode_status = text.on_cyan().white();
+            spacer_len -= text.len();
+            help_text = String::from("ENTER - Open, A - New, R - Rename, D - Delete");
+        }
+        Mode::MenuMode => {
+            let text = "MENU";
+            mode_status = text.on_cyan().white();
+            spacer_len -= text.len();
+            help_text = String::from("ENTER - Select, ESC - Back");
+        }
+        Mode::PaletteMode => {
+            let text = "COMMANDS";
+            mode_status = text.on_cyan().white();
+            spacer_len -= text.len();
+            help_text = String::from("ENTER - Run, ESC - Back");
         }
         _ => {
             mode_status = "something went wrong".on_red();
-            help_text = "??!!";
+            help_text = String::from("??!!");
         }
     }
 
+    // the start of a key sequence shows until the sequence is finished
+    if let Some(keys) = keymap.describe_pending() {
+        help_text = keys + " …";
+    }
+
+    // messages take the place of the help text when both don't fit
+    let available = spacer_len.saturating_sub(line_info.chars().count() + 2);
+    let help_text = match info_text.chars().count() + help_text.chars().count() > available {
+        true => "",
+        false => &help_text,
+    };
+
     // print!("{}", on_main(" "));
     print!("{}", mode_status);
     print!("{}", on_main(" "));
     print!("{}", on_main(help_text).dark_grey());
     print!("{}", on_main(" "));
-    print!("{}", info_text.on_grey());
 
+    // long messages get cut off instead of pushing the line info out
+    let free_space =
+        spacer_len.saturating_sub(help_text.chars().count() + line_info.chars().count() + 2);
+    let info_text: String = info_text.chars().take(free_space).collect();
+
+    print!("{}", info_text.clone().on_grey());
     print!(
         "{}",
-        on_main(&str::repeat(
-            " ",
-            spacer_len - help_text.len() - line_info.len() - 1 + 4 - info_text.len() - 1
-        ))
+        on_main(&str::repeat(" ", free_space - info_text.chars().count()))
     );
 
+    print!("{}", on_main(&warning).yellow());
     print!("{}", on_main(&line_info).dark_grey());
 
     Ok(())
 }
 
 fn generate_line_info(
+    buffer: &Buffer,
     current_line: &usize,
     current_char: &usize,
     current_scroll: &usize,
 ) -> String {
     let mut line_info = String::new();
 
+    if let Some(hex) = &buffer.hex {
+        line_info.push_str(&format!("Offset: 0x{:08x}", hex.get_cursor()));
+        line_info.push_str(&format!(" │ {} bytes │ Hex ", hex.bytes.len()));
+        return line_info;
+    }
+
     line_info.push_str("Sc: ");
     line_info.push_str(&current_scroll.to_string());
 
@@ -76,6 +152,12 @@ fn generate_line_info(
     line_info.push_str(&current_line.to_string());
     line_info.push_str(" Ch: ");
     line_info.push_str(&current_char.to_string());
-    line_info.push_str(" │ Spaces: 4 │ Plain Text ");
+    line_info.push_str(" │ ");
+    line_info.push_str(&buffer.indent.describe());
+    line_info.push_str(" │ ");
+    line_info.push_str(&buffer.file_format.describe());
+    line_info.push_str(" │ ");
+    line_info.push_str(buffer.language.name);
+    line_info.push(' ');
     return line_info;
 }
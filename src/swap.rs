@@ -0,0 +1,96 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::buffer::Buffer;
+use crate::functions::hash_of;
+
+// keeps a copy of unsaved changes next to the file, so they survive a crash
+pub struct Swap {
+    last_write: Instant,
+    // hash of what's in the swap file right now
+    last_hash: Option<u64>,
+}
+
+impl Swap {
+    pub fn new() -> Self {
+        Self {
+            last_write: Instant::now(),
+            last_hash: None,
+        }
+    }
+
+    // writes the swap file if the buffer changed since the last time
+    pub fn update(&mut self, buffer: &Buffer, interval: u64) -> io::Result<()> {
+        if buffer.file_name.is_empty() || !buffer.is_modified() {
+            return Ok(());
+        }
+        if self.last_write.elapsed() < Duration::from_secs(interval) {
+            return Ok(());
+        }
+
+        let bytes = buffer.to_bytes()?;
+        let hash = hash_of(&bytes);
+        self.last_write = Instant::now();
+
+        if self.last_hash != Some(hash) {
+            fs::write(swap_path(&buffer.file_path), bytes)?;
+            self.last_hash = Some(hash);
+        }
+
+        Ok(())
+    }
+
+    // called once the changes are saved or thrown away
+    pub fn remove(&mut self, buffer: &Buffer) {
+        if self.last_hash.take().is_some() {
+            let _ = fs::remove_file(swap_path(&buffer.file_path));
+        }
+    }
+}
+
+// contents of a swap file left behind by an earlier session, if they differ from the file
+pub fn find_leftover(buffer: &Buffer) -> Option<Vec<u8>> {
+    let swapped = fs::read(swap_path(&buffer.file_path)).ok()?;
+
+    match fs::read(&buffer.file_path) {
+        Ok(bytes) if bytes == swapped => {
+            delete_leftover(buffer);
+            None
+        }
+        _ => Some(swapped),
+    }
+}
+
+pub fn delete_leftover(buffer: &Buffer) {
+    let _ = fs::remove_file(swap_path(&buffer.file_path));
+}
+
+// moves the leftover out of the way, so this session's swap file doesn't
+// overwrite it. returns where it went
+pub fn keep_leftover(buffer: &Buffer) -> io::Result<PathBuf> {
+    let leftover = swap_path(&buffer.file_path);
+
+    let mut n = 1;
+    let kept = loop {
+        let kept = PathBuf::from(format!("{}.{}", leftover.display(), n));
+        if !kept.exists() {
+            break kept;
+        }
+        n += 1;
+    };
+
+    fs::rename(&leftover, &kept)?;
+    Ok(kept)
+}
+
+fn swap_path(file_path: &str) -> PathBuf {
+    let path = Path::new(file_path);
+    let name = match path.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => String::new(),
+    };
+
+    path.with_file_name(format!(".{}.pico-swp", name))
+}
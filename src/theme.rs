@@ -0,0 +1,53 @@
+use std::sync::Mutex;
+
+use crossterm::style::Color;
+
+// the colors the editor is drawn with, picked in the Color menu or the config file
+#[derive(Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub name: &'static str,
+    pub main: (u8, u8, u8),
+    pub secondary: (u8, u8, u8),
+}
+
+pub const THEMES: &[Theme] = &[
+    Theme {
+        name: "Pico",
+        main: (35, 35, 45),
+        secondary: (47, 47, 56),
+    },
+    Theme {
+        name: "Midnight",
+        main: (20, 24, 36),
+        secondary: (31, 37, 54),
+    },
+    Theme {
+        name: "Forest",
+        main: (28, 34, 29),
+        secondary: (40, 48, 41),
+    },
+];
+
+// every draw function reads the colors, so they are kept in one place
+// instead of being passed around
+static CURRENT: Mutex<Theme> = Mutex::new(THEMES[0]);
+
+pub fn set_theme(theme: Theme) {
+    *CURRENT.lock().unwrap() = theme;
+}
+
+pub fn current() -> Theme {
+    *CURRENT.lock().unwrap()
+}
+
+// a theme by its name, in any case
+pub fn find_theme(name: &str) -> Option<Theme> {
+    THEMES
+        .iter()
+        .find(|theme| theme.name.eq_ignore_ascii_case(name))
+        .copied()
+}
+
+pub fn rgb((r, g, b): (u8, u8, u8)) -> Color {
+    Color::Rgb { r, g, b }
+}
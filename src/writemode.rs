@@ -3,8 +3,12 @@ use std::io;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
 use crate::{
-    functions::clear, jump_to_editor_point, move_down, move_left, move_right, move_up,
-    ChangedLineType,
+    brackets::Brackets,
+    functions::{byte_index, char_count, clear},
+    indent::{leading_whitespace, Indent},
+    jump_to_editor_point,
+    language::Language,
+    move_down, move_left, move_right, move_up, ChangedLineType,
 };
 
 // handle key event for write mode
@@ -16,6 +20,11 @@ pub fn handle_key_event(
     current_scroll: &mut usize,
     editor_height: &usize,
     lines: &mut Vec<String>,
+    indent: Indent,
+    language: &Language,
+    auto_close: bool,
+    auto_closed: &mut Vec<(usize, usize)>,
+    bracket_cache: &mut Option<Brackets>,
     initial: bool,
 ) -> io::Result<ChangedLineType> {
     let mut changed_line = ChangedLineType::None;
@@ -63,21 +72,39 @@ pub fn handle_key_event(
             }
         }
         KeyCode::Enter => {
-            *current_line += 1;
-            lines.insert(*current_line, String::new());
+            auto_closed.clear();
+            let split_at = byte_index(&lines[*current_line], *current_char);
+            let rest = lines[*current_line].split_off(split_at);
+            let rest = rest.trim_start();
+            let before = &lines[*current_line];
+
+            // the new line keeps the indentation, with one level more after
+            // an opening bracket or one of the language's tokens
+            let outer = leading_whitespace(before).to_string();
+            let mut inner = outer.clone();
+            if language.indents_after(before) {
+                inner += &indent.unit();
+            }
 
-            if *current_char < lines[*current_line - 1].len() {
-                lines[*current_line] = lines[*current_line - 1]
-                    [*current_char..lines[*current_line - 1].len()]
-                    .to_string();
+            // the closing bracket of `{|}` gets a line of its own
+            let closes = match rest.chars().next() {
+                Some(c) => language
+                    .opening_of(c)
+                    .is_some_and(|open| before.trim_end().ends_with(open)),
+                None => false,
+            };
 
-                lines[*current_line - 1] = lines[*current_line - 1][0..*current_char].to_string();
+            *current_line += 1;
+            *current_char = char_count(&inner);
+            if closes {
+                lines.insert(*current_line, outer + rest);
+                lines.insert(*current_line, inner);
+            } else {
+                lines.insert(*current_line, inner + rest);
             }
 
             jump_to_editor_point(current_line, current_scroll, editor_height);
 
-            *current_char = 0;
-
             clear()?;
             if initial {
                 lines.remove(0);
@@ -89,39 +116,88 @@ pub fn handle_key_event(
         }
 
         KeyCode::Tab => {
+            auto_closed.clear();
             *current_char += 1;
             // changed_line = ChangedLineType::Line(*current_line);
             changed_line = ChangedLineType::All;
-            if *current_char >= lines[*current_line].len() {
-                lines[*current_line].push_str("    ");
-            } else {
-                lines[*current_line] = lines[*current_line][0..*current_char - 1].to_string()
-                    + "    "
-                    + &lines[*current_line][*current_char - 1..lines[*current_line].len()]
-            }
+            let index = byte_index(&lines[*current_line], *current_char - 1);
+            let unit = indent.unit();
+            lines[*current_line].insert_str(index, &unit);
 
-            *current_char += 3;
+            *current_char += unit.len() - 1;
 
             clear()?;
         }
+        // one level less, wherever the cursor is
+        KeyCode::BackTab => {
+            auto_closed.clear();
+            let removed = indent.outdent_len(&lines[*current_line]);
+            lines[*current_line].drain(..removed);
+            *current_char = current_char.saturating_sub(removed);
+            changed_line = ChangedLineType::All;
+            clear()?;
+        }
 
         KeyCode::Char(c) => {
             *info_text = String::new();
             clear()?;
+
+            // a closing bracket at the start of a line lines up with the
+            // line of its opening one, or goes one level out without one
+            let index = byte_index(&lines[*current_line], *current_char);
+            if language.opening_of(c).is_some() && lines[*current_line][..index].trim().is_empty() {
+                let opening = bracket_cache
+                    .get_or_insert_with(|| Brackets::scan(lines, language))
+                    .open_before(*current_line, 0)
+                    .filter(|(_, _, open)| language.closing_of(*open) == Some(c));
+                let new_indent = match opening {
+                    Some((line, _, _)) => leading_whitespace(&lines[line]).to_owned(),
+                    None => {
+                        let removed = indent.outdent_len(&lines[*current_line]).min(index);
+                        lines[*current_line][removed..index].to_owned()
+                    }
+                };
+
+                lines[*current_line].replace_range(..index, &new_indent);
+                let new_char = char_count(&new_indent);
+                shift(
+                    auto_closed,
+                    *current_line,
+                    0,
+                    new_char as isize - *current_char as isize,
+                );
+                *current_char = new_char;
+            }
+
+            let at = (*current_line, *current_char);
             *current_char += 1;
             // changed_line = ChangedLineType::Line(*current_line);
             changed_line = ChangedLineType::All;
             jump_to_editor_point(current_line, current_scroll, editor_height);
-            if *current_char >= lines[*current_line].len() {
-                lines[*current_line].push(c);
+            let index = byte_index(&lines[*current_line], *current_char - 1);
+            let line = &mut lines[*current_line];
+            let next = line[index..].chars().next();
+
+            // typing the closing char that was inserted along with its
+            // opening one only moves the cursor past it
+            if next == Some(c) && auto_closed.contains(&at) {
+                auto_closed.retain(|closer| *closer != at);
             } else {
-                lines[*current_line] = lines[*current_line][0..*current_char - 1].to_string()
-                    + &c.to_string()
-                    + &lines[*current_line][*current_char - 1..lines[*current_line].len()]
+                let previous = line[..index].chars().last();
+                let close = language
+                    .auto_close(c, previous, next)
+                    .filter(|_| auto_close);
+                shift(auto_closed, at.0, at.1, 1 + close.is_some() as isize);
+                if let Some(close) = close {
+                    line.insert(index, close);
+                    auto_closed.push((at.0, at.1 + 1));
+                }
+                line.insert(index, c);
             }
         }
         KeyCode::Backspace => {
             if *current_char == 0 {
+                auto_closed.clear();
                 *info_text = String::new();
                 if *current_line == 0 {
                     return Ok(ChangedLineType::None);
@@ -132,17 +208,38 @@ pub fn handle_key_event(
                 lines.remove(*current_line);
 
                 *current_line -= 1;
-                *current_char = lines[*current_line].len() - copied_line.len();
+                *current_char = char_count(&lines[*current_line]) - char_count(&copied_line);
                 *current_char += 1;
             } else if key_event.modifiers == KeyModifiers::CONTROL {
                 // lines[*current_line] += "test";
                 // cannot add this as long as crossterm doesn't fix backspace + keymod issue
-            } else if *current_char >= lines[*current_line].len() {
-                lines[*current_line].pop();
-                // clear_all()?;
             } else {
-                lines[*current_line] = lines[*current_line][0..*current_char - 1].to_string()
-                    + &lines[*current_line][*current_char..lines[*current_line].len()]
+                let index = byte_index(&lines[*current_line], *current_char);
+                let line = &mut lines[*current_line];
+                let empty_pair = line[..index].chars().last().and_then(|open| {
+                    let close = language.closing_of(open)?;
+                    (auto_close && line[index..].starts_with(close)).then_some((open, close))
+                });
+
+                if let Some((open, close)) = empty_pair {
+                    // both halves of an empty pair go
+                    line.drain(index - open.len_utf8()..index + close.len_utf8());
+                    let closer = (*current_line, *current_char);
+                    auto_closed.retain(|c| *c != closer);
+                    shift(auto_closed, *current_line, *current_char, -2);
+                } else {
+                    // inside the indentation a whole level gets removed
+                    let range = indent.backspace_range(&line[..index]);
+                    let removed = char_count(&line[range.clone()]);
+                    line.drain(range);
+                    shift(
+                        auto_closed,
+                        *current_line,
+                        *current_char,
+                        -(removed as isize),
+                    );
+                    *current_char -= removed - 1;
+                }
             }
             *current_char -= 1;
             // changed_line = ChangedLineType::Line(*current_line);
@@ -160,3 +257,13 @@ pub fn handle_key_event(
 
     return Ok(changed_line);
 }
+
+// moves the auto inserted closers at or after `from` on a line along with
+// chars typed or removed in front of them
+fn shift(auto_closed: &mut [(usize, usize)], line: usize, from: usize, by: isize) {
+    for (l, c) in auto_closed.iter_mut() {
+        if *l == line && *c >= from {
+            *c = c.saturating_add_signed(by);
+        }
+    }
+}